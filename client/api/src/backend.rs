@@ -275,6 +275,7 @@ pub trait AuxStore {
 /// An `Iterator` that iterates keys in a given block under a prefix.
 pub struct KeyIterator<'a, State, Block> {
 	state: State,
+	child_storage: Option<ChildInfo>,
 	prefix: Option<&'a StorageKey>,
 	current_key: Vec<u8>,
 	_phantom: PhantomData<Block>,
@@ -285,6 +286,23 @@ impl <'a, State, Block> KeyIterator<'a, State, Block> {
 	pub fn new(state: State, prefix: Option<&'a StorageKey>, current_key: Vec<u8>) -> Self {
 		Self {
 			state,
+			child_storage: None,
+			prefix,
+			current_key,
+			_phantom: PhantomData,
+		}
+	}
+
+	/// Create a `KeyIterator` instance for a child storage.
+	pub fn new_child(
+		state: State,
+		child_info: ChildInfo,
+		prefix: Option<&'a StorageKey>,
+		current_key: Vec<u8>,
+	) -> Self {
+		Self {
+			state,
+			child_storage: Some(child_info),
 			prefix,
 			current_key,
 			_phantom: PhantomData,
@@ -299,8 +317,11 @@ impl<'a, State, Block> Iterator for KeyIterator<'a, State, Block> where
 	type Item = StorageKey;
 
 	fn next(&mut self) -> Option<Self::Item> {
-		let next_key = self.state
-			.next_storage_key(&self.current_key)
+		let next_key = if let Some(child_info) = self.child_storage.as_ref() {
+			self.state.next_child_storage_key(child_info, &self.current_key)
+		} else {
+			self.state.next_storage_key(&self.current_key)
+		}
 			.ok()
 			.flatten()?;
 		// this terminates the iterator the first time it fails.
@@ -319,6 +340,14 @@ pub trait StorageProvider<Block: BlockT, B: Backend<Block>> {
 	/// Given a `BlockId` and a key, return the value under the key in that block.
 	fn storage(&self, id: &BlockId<Block>, key: &StorageKey) -> sp_blockchain::Result<Option<StorageData>>;
 
+	/// Given a `BlockId` and several keys, return the values under the keys in that block.
+	/// The state of the block is looked up once for all the keys.
+	fn storage_values(
+		&self,
+		id: &BlockId<Block>,
+		keys: &[StorageKey],
+	) -> sp_blockchain::Result<Vec<Option<StorageData>>>;
+
 	/// Given a `BlockId` and a key prefix, return the matching storage keys in that block.
 	fn storage_keys(&self, id: &BlockId<Block>, key_prefix: &StorageKey) -> sp_blockchain::Result<Vec<StorageKey>>;
 
@@ -356,6 +385,16 @@ pub trait StorageProvider<Block: BlockT, B: Backend<Block>> {
 		key_prefix: &StorageKey
 	) -> sp_blockchain::Result<Vec<StorageKey>>;
 
+	/// Given a `BlockId`, a key prefix, and a child storage key, return a `KeyIterator` that
+	/// iterates the matching child storage keys in that block.
+	fn child_storage_keys_iter<'a>(
+		&self,
+		id: &BlockId<Block>,
+		child_info: ChildInfo,
+		prefix: Option<&'a StorageKey>,
+		start_key: Option<&StorageKey>
+	) -> sp_blockchain::Result<KeyIterator<'a, B::State, Block>>;
+
 	/// Given a `BlockId`, a key and a child storage key, return the hash under the key in that block.
 	fn child_storage_hash(
 		&self,
@@ -385,6 +424,16 @@ pub trait StorageProvider<Block: BlockT, B: Backend<Block>> {
 		storage_key: Option<&PrefixedStorageKey>,
 		key: &StorageKey
 	) -> sp_blockchain::Result<Vec<(NumberFor<Block>, u32)>>;
+
+	/// Get the top-level keys under `prefix` that have been written by the given block.
+	/// Works only for runtimes that are supporting changes tries.
+	///
+	/// Keys that were written with their previous value are included.
+	fn changed_keys(
+		&self,
+		id: &BlockId<Block>,
+		prefix: Option<&StorageKey>,
+	) -> sp_blockchain::Result<Vec<StorageKey>>;
 }
 
 /// Client backend.
@@ -32,6 +32,7 @@ use sc_service::{
 };
 use sc_telemetry::TelemetryEndpoints;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
 use structopt::StructOpt;
 
 /// The `run` command used to run a node.
@@ -122,6 +123,36 @@ pub struct RunCmd {
 	#[structopt(long = "ws-max-connections", value_name = "COUNT")]
 	pub ws_max_connections: Option<usize>,
 
+	/// Maximum execution time of a runtime call made through `state_call` or `state_callTrace`,
+	/// in milliseconds.
+	///
+	/// Calls running longer than this are answered with an error. Their execution can't be
+	/// interrupted, so new calls are refused while 4 timed out ones are still running.
+	/// Unbounded by default.
+	#[structopt(long = "rpc-call-timeout", value_name = "MILLISECONDS")]
+	pub rpc_call_timeout: Option<u64>,
+
+	/// Maximum number of heavy state RPC requests processed concurrently.
+	///
+	/// Heavy requests are key and pair listings, storage queries over block ranges, multi-key
+	/// read proofs and block tracing. Requests above this limit are rejected. Unlimited by default.
+	#[structopt(long = "rpc-max-heavy-requests", value_name = "COUNT")]
+	pub rpc_max_heavy_requests: Option<usize>,
+
+	/// Runtime method that may not be called through `state_call`, e.g. `Core_execute_block`.
+	///
+	/// Can be passed multiple times.
+	#[structopt(long = "rpc-forbid-call", value_name = "METHOD")]
+	pub rpc_forbidden_calls: Vec<String>,
+
+	/// Refuse to return compressed results from `state_getKeys` and `state_getPairs`.
+	#[structopt(long = "rpc-disable-compression")]
+	pub rpc_disable_compression: bool,
+
+	/// Reject unsafe and heavy state queries with a retry hint while the node is major syncing.
+	#[structopt(long = "rpc-reject-while-syncing")]
+	pub rpc_reject_while_syncing: bool,
+
 	/// Specify browser Origins allowed to access the HTTP & WS RPC servers.
 	///
 	/// A comma-separated list of origins (protocol://domain or special `null`
@@ -376,6 +407,26 @@ impl CliConfiguration for RunCmd {
 		Ok(self.ws_max_connections)
 	}
 
+	fn rpc_call_timeout(&self) -> Result<Option<Duration>> {
+		Ok(self.rpc_call_timeout.map(Duration::from_millis))
+	}
+
+	fn rpc_max_heavy_requests(&self) -> Result<Option<usize>> {
+		Ok(self.rpc_max_heavy_requests)
+	}
+
+	fn rpc_forbidden_calls(&self) -> Result<Vec<String>> {
+		Ok(self.rpc_forbidden_calls.clone())
+	}
+
+	fn rpc_disable_compression(&self) -> Result<bool> {
+		Ok(self.rpc_disable_compression)
+	}
+
+	fn rpc_reject_while_syncing(&self) -> Result<bool> {
+		Ok(self.rpc_reject_while_syncing)
+	}
+
 	fn rpc_cors(&self, is_dev: bool) -> Result<Option<Vec<String>>> {
 		Ok(self
 			.rpc_cors
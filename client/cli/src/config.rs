@@ -36,6 +36,7 @@ use sc_service::{ChainSpec, TracingReceiver, KeepBlocks, TransactionStorageMode}
 use sc_tracing::logging::LoggerBuilder;
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::time::Duration;
 
 /// The maximum number of characters for a node name.
 pub(crate) const NODE_NAME_MAX_LENGTH: usize = 64;
@@ -358,6 +359,43 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 		Ok(None)
 	}
 
+	/// Get the maximum execution time of runtime calls made through RPC (`None` if unbounded).
+	///
+	/// By default this is `None`.
+	fn rpc_call_timeout(&self) -> Result<Option<Duration>> {
+		Ok(None)
+	}
+
+	/// Get the maximum number of heavy state RPC requests processed concurrently
+	/// (`None` if unlimited).
+	///
+	/// By default this is `None`.
+	fn rpc_max_heavy_requests(&self) -> Result<Option<usize>> {
+		Ok(None)
+	}
+
+	/// Get the runtime methods that may not be called through `state_call`.
+	///
+	/// By default this is empty.
+	fn rpc_forbidden_calls(&self) -> Result<Vec<String>> {
+		Ok(Vec::new())
+	}
+
+	/// Returns `true` if compressed results of `state_getKeys` and `state_getPairs` are refused.
+	///
+	/// By default this is `false`.
+	fn rpc_disable_compression(&self) -> Result<bool> {
+		Ok(false)
+	}
+
+	/// Returns `true` if unsafe and heavy state queries are rejected while the node is major
+	/// syncing.
+	///
+	/// By default this is `false`.
+	fn rpc_reject_while_syncing(&self) -> Result<bool> {
+		Ok(false)
+	}
+
 	/// Get the RPC cors (`None` if disabled)
 	///
 	/// By default this is `Some(Vec::new())`.
@@ -526,6 +564,11 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 			rpc_ipc: self.rpc_ipc()?,
 			rpc_methods: self.rpc_methods()?,
 			rpc_ws_max_connections: self.rpc_ws_max_connections()?,
+			rpc_call_timeout: self.rpc_call_timeout()?,
+			rpc_max_heavy_requests: self.rpc_max_heavy_requests()?,
+			rpc_forbidden_calls: self.rpc_forbidden_calls()?,
+			rpc_disable_compression: self.rpc_disable_compression()?,
+			rpc_reject_while_syncing: self.rpc_reject_while_syncing()?,
 			rpc_cors: self.rpc_cors(is_dev)?,
 			prometheus_config: self.prometheus_config(DCV::prometheus_listen_port())?,
 			telemetry_endpoints,
@@ -119,6 +119,8 @@ pub struct NetworkService<B: BlockT + 'static, H: ExHashT> {
 	external_addresses: Arc<Mutex<Vec<Multiaddr>>>,
 	/// Are we actively catching up with the chain?
 	is_major_syncing: Arc<AtomicBool>,
+	/// Best block number announced by any of our peers.
+	best_seen_block: Arc<Mutex<Option<NumberFor<B>>>>,
 	/// Local copy of the `PeerId` of the local node.
 	local_peer_id: PeerId,
 	/// Bandwidth logging system. Can be queried to know the average bandwidth consumed.
@@ -242,6 +244,7 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 
 		let num_connected = Arc::new(AtomicUsize::new(0));
 		let is_major_syncing = Arc::new(AtomicBool::new(false));
+		let best_seen_block = Arc::new(Mutex::new(None));
 
 		// Build the swarm.
 		let client = params.chain.clone();
@@ -406,6 +409,7 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			external_addresses: external_addresses.clone(),
 			num_connected: num_connected.clone(),
 			is_major_syncing: is_major_syncing.clone(),
+			best_seen_block: best_seen_block.clone(),
 			peerset: peerset_handle,
 			local_peer_id,
 			to_worker,
@@ -427,6 +431,7 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			external_addresses,
 			num_connected,
 			is_major_syncing,
+			best_seen_block,
 			network_service: swarm,
 			service,
 			import_queue: params.import_queue,
@@ -981,6 +986,11 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 		self.is_major_syncing.load(Ordering::Relaxed)
 	}
 
+	/// Best block number announced by any of our peers, if any.
+	pub fn best_seen_block(&self) -> Option<NumberFor<B>> {
+		*self.best_seen_block.lock()
+	}
+
 	/// Start getting a value from the DHT.
 	///
 	/// This will generate either a `ValueFound` or a `ValueNotFound` event and pass it as an
@@ -1365,6 +1375,8 @@ pub struct NetworkWorker<B: BlockT + 'static, H: ExHashT> {
 	num_connected: Arc<AtomicUsize>,
 	/// Updated by the `NetworkWorker` and loaded by the `NetworkService`.
 	is_major_syncing: Arc<AtomicBool>,
+	/// Updated by the `NetworkWorker` and loaded by the `NetworkService`.
+	best_seen_block: Arc<Mutex<Option<NumberFor<B>>>>,
 	/// The network service that can be extracted and shared through the codebase.
 	service: Arc<NetworkService<B, H>>,
 	/// The *actual* network.
@@ -1857,6 +1869,8 @@ impl<B: BlockT + 'static, H: ExHashT> Future for NetworkWorker<B, H> {
 		this.tx_handler_controller.set_gossip_enabled(!is_major_syncing);
 
 		this.is_major_syncing.store(is_major_syncing, Ordering::Relaxed);
+		*this.best_seen_block.lock() =
+			this.network_service.behaviour_mut().user_protocol_mut().best_seen_block();
 
 		if let Some(metrics) = this.metrics.as_ref() {
 			for (proto, buckets) in this.network_service.behaviour_mut().num_entries_per_kbucket() {
@@ -51,6 +51,46 @@ pub enum Error {
 		/// Maximum allowed value
 		max: u32,
 	},
+	/// The block is known, but its state has already been pruned.
+	#[display(fmt = "State already discarded for block {}", hash)]
+	StatePruned {
+		/// Hash of the block.
+		hash: String,
+	},
+	/// Runtime call didn't finish within the configured time.
+	#[display(fmt = "Runtime call exceeded the maximum execution time of {} ms", timeout_ms)]
+	CallTimeout {
+		/// Maximum allowed execution time in milliseconds.
+		timeout_ms: u64,
+	},
+	/// Too many heavy requests are already being processed.
+	#[display(fmt = "Too many concurrent heavy requests, at most {} are allowed", max)]
+	TooManyRequests {
+		/// Maximum number of heavy requests processed concurrently.
+		max: u32,
+	},
+	/// A request parameter has an invalid value.
+	#[display(fmt = "Invalid parameter `{}`: {}", name, reason)]
+	InvalidParameter {
+		/// Name of the parameter.
+		name: String,
+		/// Why the value is invalid.
+		reason: String,
+	},
+	/// The runtime method is not allowed to be called through RPC.
+	#[display(fmt = "Calling runtime method {} is forbidden", method)]
+	MethodForbidden {
+		/// Name of the runtime method.
+		method: String,
+	},
+	/// The node is still catching up with the network, so its state may be incomplete.
+	#[display(fmt = "Node is syncing (best block {}, target {}), retry against a synced node", best, target)]
+	NodeSyncing {
+		/// Number of the best block of the node.
+		best: u64,
+		/// Number of the best block announced by the network.
+		target: u64,
+	},
 	/// Call to an unsafe RPC was denied.
 	UnsafeRpcCalled(crate::policy::UnsafeRpcError),
 }
@@ -80,6 +120,36 @@ impl From<Error> for rpc::Error {
 				message: format!("{}", e),
 				data: None,
 			},
+			Error::StatePruned { .. } => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 3),
+				message: format!("{}", e),
+				data: None,
+			},
+			Error::CallTimeout { .. } => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 4),
+				message: format!("{}", e),
+				data: None,
+			},
+			Error::TooManyRequests { .. } => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 5),
+				message: format!("{}", e),
+				data: None,
+			},
+			Error::InvalidParameter { ref name, ref reason } => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 6),
+				message: format!("{}", e),
+				data: Some(serde_json::json!({ "name": name, "reason": reason })),
+			},
+			Error::MethodForbidden { .. } => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 7),
+				message: format!("{}", e),
+				data: None,
+			},
+			Error::NodeSyncing { best, target } => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 8),
+				message: format!("{}", e),
+				data: Some(serde_json::json!({ "best": best, "target": target, "retry": true })),
+			},
 			e => errors::internal(e),
 		}
 	}
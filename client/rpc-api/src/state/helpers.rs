@@ -18,7 +18,7 @@
 
 //! Substrate state API helpers.
 
-use sp_core::Bytes;
+use sp_core::{Bytes, storage::{PrefixedStorageKey, StorageData, StorageKey}};
 use serde::{Serialize, Deserialize};
 
 /// ReadProof struct returned by the RPC
@@ -30,3 +30,137 @@ pub struct ReadProof<Hash> {
 	/// A proof used to prove that storage entries are included in the storage trie
 	pub proof: Vec<Bytes>,
 }
+
+/// Storage value together with the block it was read at.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageWithContext<Hash> {
+	/// Value of the storage entry, if any.
+	pub value: Option<StorageData>,
+	/// Hash of the block the value was read at.
+	pub block: Hash,
+	/// State root of the block the value was read at.
+	pub state_root: Hash,
+}
+
+/// Storage value together with the proof of it at the block it was read at.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifiableStorage<Hash> {
+	/// Value of the storage entry, if any.
+	pub value: Option<StorageData>,
+	/// Hash of the block the value was read at.
+	pub block: Hash,
+	/// State root of the block the value was read at.
+	pub state_root: Hash,
+	/// Proof of the value against `state_root`.
+	pub proof: Vec<Bytes>,
+}
+
+/// Value, hash and size of a storage entry, all read at the same block.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageDetails<Hash> {
+	/// Value of the storage entry, if any.
+	pub value: Option<StorageData>,
+	/// Hash of the value of the storage entry, if any.
+	pub hash: Option<Hash>,
+	/// Size of the storage entry, as returned by `state_getStorageSize`.
+	pub size: Option<u64>,
+}
+
+/// Storage item a storage key belongs to, as described by the runtime metadata.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageKeyInfo {
+	/// Name of the pallet declaring the storage item.
+	pub pallet: String,
+	/// Name of the storage item.
+	pub item: String,
+	/// Hashers applied to the keys of the storage item, empty for plain values.
+	pub key_hashers: Vec<String>,
+}
+
+/// Result of a runtime call along with the storage writes it made.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallTrace {
+	/// Bytes returned by the runtime call.
+	pub result: Bytes,
+	/// Top-level storage entries written by the call, `None` for removed entries.
+	pub changes: Vec<(StorageKey, Option<StorageData>)>,
+}
+
+/// Aggregated size of the storage entries under a prefix.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrefixSize {
+	/// Number of keys under the prefix.
+	pub keys: u64,
+	/// Total size of the values under the prefix, in bytes.
+	pub bytes: u64,
+}
+
+/// Differences between the storage entries under two prefixes.
+///
+/// Keys are given with their prefix stripped.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrefixDiff {
+	/// Keys present only under the first prefix.
+	pub only_in_a: Vec<StorageKey>,
+	/// Keys present only under the second prefix.
+	pub only_in_b: Vec<StorageKey>,
+	/// Keys present under both prefixes with different values.
+	pub differing: Vec<StorageKey>,
+}
+
+/// Best and finalized block of the chain.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainTips<Hash> {
+	/// Hash of the best block.
+	pub best: Hash,
+	/// Hash of the last finalized block.
+	pub finalized: Hash,
+}
+
+/// Result of a runtime call, encoded as requested by the caller.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CallResult {
+	/// The result as a hex string.
+	Hex(Bytes),
+	/// The result as an array of bytes.
+	Array(Vec<u8>),
+}
+
+/// Result of a request that may have been compressed on request of the caller.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MaybeCompressed<T> {
+	/// The result as is.
+	Plain(T),
+	/// The JSON encoding of the result, compressed.
+	Compressed(CompressedBlob),
+}
+
+/// Compressed blob returned by the RPC.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompressedBlob {
+	/// Codec the blob is compressed with.
+	pub codec: String,
+	/// Base64 encoded compressed bytes.
+	pub data: String,
+}
+
+/// Batch of storage entries sent by `state_exportSnapshot`.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotBatch {
+	/// Child trie the entries belong to, `None` for the top-level trie.
+	pub child: Option<PrefixedStorageKey>,
+	/// Storage entries of the batch.
+	pub pairs: Vec<(StorageKey, StorageData)>,
+}
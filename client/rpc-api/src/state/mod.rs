@@ -25,31 +25,91 @@ use jsonrpc_core::Result as RpcResult;
 use jsonrpc_derive::rpc;
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
 use sp_core::Bytes;
-use sp_core::storage::{StorageKey, StorageData, StorageChangeSet};
+use sp_core::storage::{StorageKey, PrefixedStorageKey, StorageData, StorageChangeSet};
+use sp_rpc::number::NumberOrHex;
 use sp_version::RuntimeVersion;
 use self::error::FutureResult;
 
 pub use self::gen_client::Client as StateClient;
-pub use self::helpers::ReadProof;
+pub use self::helpers::{
+	CallResult, CallTrace, ChainTips, CompressedBlob, MaybeCompressed, PrefixDiff, PrefixSize, ReadProof,
+	SnapshotBatch, StorageDetails, StorageKeyInfo, StorageWithContext, VerifiableStorage,
+};
 
 /// Substrate state API
+///
+/// Methods taking an optional block hash resolve `None` to the best block at the time of
+/// the call. This is not atomic across calls: a block imported in between two calls moves
+/// the best block, so the calls may read different states. Clients that need a consistent
+/// view over several calls should resolve the best block hash once (e.g. with
+/// `chain_getBlockHash`) and pass it explicitly.
 #[rpc]
 pub trait StateApi<Hash> {
 	/// RPC Metadata
 	type Metadata;
 
 	/// Call a contract at a block's state.
+	///
+	/// The result is a hex string, unless `as_hex` is `false` in which case it is an array
+	/// of bytes.
 	#[rpc(name = "state_call", alias("state_callAt"))]
-	fn call(&self, name: String, bytes: Bytes, hash: Option<Hash>) -> FutureResult<Bytes>;
+	fn call(
+		&self,
+		name: String,
+		bytes: Bytes,
+		hash: Option<Hash>,
+		as_hex: Option<bool>,
+	) -> FutureResult<CallResult>;
+
+	/// Call a runtime method at a block's state and return its result decoded as JSON.
+	///
+	/// The runtime metadata doesn't describe the types of runtime APIs, so only the results
+	/// of well known methods can be decoded: `Core_version`, `Metadata_metadata`,
+	/// `AccountNonceApi_account_nonce` and `AuraApi_slot_duration`. Methods that write to
+	/// the node's keystore, such as `SessionKeys_generate_session_keys`, are not served.
+	#[rpc(name = "state_callDecoded")]
+	fn call_decoded(
+		&self,
+		name: String,
+		bytes: Bytes,
+		hash: Option<Hash>,
+	) -> FutureResult<serde_json::Value>;
+
+	/// Call a contract at a block's state, returning the storage writes it made along
+	/// with its result. The writes are discarded afterwards.
+	///
+	/// Note: requires the node to run with `--rpc-methods=Unsafe`.
+	#[rpc(name = "state_callTrace")]
+	fn call_trace(&self, name: String, bytes: Bytes, hash: Option<Hash>) -> FutureResult<CallTrace>;
 
 	/// DEPRECATED: Please use `state_getKeysPaged` with proper paging support.
 	/// Returns the keys with prefix, leave empty to get all the keys.
+	///
+	/// If `compress` is `true`, the keys are returned as a compressed blob instead.
 	#[rpc(name = "state_getKeys")]
-	fn storage_keys(&self, prefix: StorageKey, hash: Option<Hash>) -> FutureResult<Vec<StorageKey>>;
+	fn storage_keys(
+		&self,
+		prefix: StorageKey,
+		hash: Option<Hash>,
+		compress: Option<bool>,
+	) -> FutureResult<MaybeCompressed<Vec<StorageKey>>>;
 
 	/// Returns the keys with prefix, leave empty to get all the keys
+	///
+	/// If `compress` is `true`, the pairs are returned as a compressed blob instead.
 	#[rpc(name = "state_getPairs")]
-	fn storage_pairs(&self, prefix: StorageKey, hash: Option<Hash>) -> FutureResult<Vec<(StorageKey, StorageData)>>;
+	fn storage_pairs(
+		&self,
+		prefix: StorageKey,
+		hash: Option<Hash>,
+		compress: Option<bool>,
+	) -> FutureResult<MaybeCompressed<Vec<(StorageKey, StorageData)>>>;
+
+	/// Returns the prefixed storage keys of all default child tries at a block's state.
+	///
+	/// Note: requires the node to run with `--rpc-methods=Unsafe`.
+	#[rpc(name = "state_getChildKeys")]
+	fn child_keys(&self, hash: Option<Hash>) -> FutureResult<Vec<PrefixedStorageKey>>;
 
 	/// Returns the keys with prefix with pagination support.
 	/// Up to `count` keys will be returned.
@@ -67,6 +127,67 @@ pub trait StateApi<Hash> {
 	#[rpc(name = "state_getStorage", alias("state_getStorageAt"))]
 	fn storage(&self, key: StorageKey, hash: Option<Hash>) -> FutureResult<Option<StorageData>>;
 
+	/// Returns the values of several storage entries at several blocks.
+	///
+	/// The result is indexed by block, then by key. The number of keys times the
+	/// number of blocks is bounded.
+	#[rpc(name = "state_getStorageMatrix")]
+	fn storage_matrix(
+		&self,
+		keys: Vec<StorageKey>,
+		blocks: Vec<Hash>,
+	) -> FutureResult<Vec<Vec<Option<StorageData>>>>;
+
+	/// Returns a storage entry along with the block it was read at and its state root.
+	///
+	/// If no block is given, the reported block is the best block the query resolved to.
+	#[rpc(name = "state_getStorageWithContext")]
+	fn storage_with_context(
+		&self,
+		key: StorageKey,
+		hash: Option<Hash>,
+	) -> FutureResult<StorageWithContext<Hash>>;
+
+	/// Returns a storage entry along with the block it was read at, its state root and
+	/// a proof of the entry against that root.
+	///
+	/// Note: requires the node to run with `--rpc-methods=Unsafe`.
+	#[rpc(name = "state_getStorageVerified")]
+	fn storage_verified(
+		&self,
+		key: StorageKey,
+		hash: Option<Hash>,
+	) -> FutureResult<VerifiableStorage<Hash>>;
+
+	/// Returns the value, hash and size of a storage entry, all read at the same block.
+	#[rpc(name = "state_getStorageDetails")]
+	fn storage_details(
+		&self,
+		key: StorageKey,
+		hash: Option<Hash>,
+	) -> FutureResult<StorageDetails<Hash>>;
+
+	/// Returns the pallet and storage item the given key belongs to.
+	///
+	/// The key is matched against the storage prefixes in the runtime metadata of the
+	/// given block. Returns `None` if it doesn't belong to any known storage item.
+	#[rpc(name = "state_decodeStorageKey")]
+	fn decode_storage_key(&self, key: StorageKey, hash: Option<Hash>) -> FutureResult<Option<StorageKeyInfo>>;
+
+	/// Returns the storage key of a pallet storage item for the given SCALE encoded keys.
+	///
+	/// The keys are hashed with the hashers listed for the item in the runtime metadata of
+	/// the given block. Passing fewer keys than the item takes returns a partial key, usable
+	/// as a prefix. Returns `None` if the runtime has no such storage item.
+	#[rpc(name = "state_encodeStorageKey")]
+	fn encode_storage_key(
+		&self,
+		pallet: String,
+		item: String,
+		keys: Vec<Bytes>,
+		hash: Option<Hash>,
+	) -> FutureResult<Option<StorageKey>>;
+
 	/// Returns the hash of a storage entry at a block's state.
 	#[rpc(name = "state_getStorageHash", alias("state_getStorageHashAt"))]
 	fn storage_hash(&self, key: StorageKey, hash: Option<Hash>) -> FutureResult<Option<Hash>>;
@@ -75,6 +196,51 @@ pub trait StateApi<Hash> {
 	#[rpc(name = "state_getStorageSize", alias("state_getStorageSizeAt"))]
 	fn storage_size(&self, key: StorageKey, hash: Option<Hash>) -> FutureResult<Option<u64>>;
 
+	/// Returns the number of keys and the total size of the values with prefix at a block's state.
+	///
+	/// Note: requires the node to run with `--rpc-methods=Unsafe`.
+	#[rpc(name = "state_getPrefixSize")]
+	fn prefix_size(&self, prefix: StorageKey, hash: Option<Hash>) -> FutureResult<PrefixSize>;
+
+	/// Compares the storage entries under two prefixes at a block's state.
+	///
+	/// Keys are compared with their prefix stripped. At most 1000 keys are scanned in total.
+	///
+	/// Note: requires the node to run with `--rpc-methods=Unsafe`.
+	#[rpc(name = "state_diffPrefixes")]
+	fn diff_prefixes(
+		&self,
+		prefix_a: StorageKey,
+		prefix_b: StorageKey,
+		hash: Option<Hash>,
+	) -> FutureResult<PrefixDiff>;
+
+	/// Returns whether a storage entry exists at a block's state, without returning its value.
+	#[rpc(name = "state_getStorageExists")]
+	fn storage_exists(&self, key: StorageKey, hash: Option<Hash>) -> FutureResult<bool>;
+
+	/// Returns the hashes of the best and the last finalized block.
+	#[rpc(name = "state_getChainTips")]
+	fn chain_tips(&self) -> FutureResult<ChainTips<Hash>>;
+
+	/// Returns the hashes of the canonical blocks with the given numbers.
+	///
+	/// Numbers above the best block or whose block is unknown to the node resolve to `None`.
+	#[rpc(name = "state_hashesForNumbers")]
+	fn hashes_for_numbers(&self, numbers: Vec<NumberOrHex>) -> FutureResult<Vec<Option<Hash>>>;
+
+	/// Returns whether the given block is finalized.
+	#[rpc(name = "state_isFinalized")]
+	fn is_finalized(&self, hash: Hash) -> FutureResult<bool>;
+
+	/// Returns the hash of the genesis block.
+	#[rpc(name = "state_getGenesisHash")]
+	fn genesis_hash(&self) -> FutureResult<Hash>;
+
+	/// Returns the state root of a block.
+	#[rpc(name = "state_getStateRoot")]
+	fn state_root(&self, hash: Option<Hash>) -> FutureResult<Hash>;
+
 	/// Returns the runtime metadata as an opaque blob.
 	#[rpc(name = "state_getMetadata")]
 	fn metadata(&self, hash: Option<Hash>) -> FutureResult<Bytes>;
@@ -83,6 +249,12 @@ pub trait StateApi<Hash> {
 	#[rpc(name = "state_getRuntimeVersion", alias("chain_getRuntimeVersion"))]
 	fn runtime_version(&self, hash: Option<Hash>) -> FutureResult<RuntimeVersion>;
 
+	/// Get the runtime version at the canonical block with the given number.
+	///
+	/// Light nodes fetch the version with a proof from a full node.
+	#[rpc(name = "state_getRuntimeVersionAtNumber")]
+	fn runtime_version_at_number(&self, number: NumberOrHex) -> FutureResult<RuntimeVersion>;
+
 	/// Query historical storage entries (by key) starting from a block given as the second parameter.
 	///
 	/// NOTE This first returned result contains the initial state of storage for all keys.
@@ -103,10 +275,55 @@ pub trait StateApi<Hash> {
 		at: Option<Hash>,
 	) -> FutureResult<Vec<StorageChangeSet<Hash>>>;
 
+	/// Returns the keys whose values were changed by the blocks after `from` up to `to`,
+	/// optionally restricted to keys with the given prefix.
+	///
+	/// Blocks covered by changes tries are looked up in them, and at most 256 such blocks may
+	/// be queried. Other blocks have to be executed again, which costs as much as importing
+	/// them, so at most 16 of them may be queried and each execution is subject to the
+	/// runtime call timeout.
+	///
+	/// Note: requires the node to run with `--rpc-methods=Unsafe`.
+	#[rpc(name = "state_changedKeys")]
+	fn changed_keys(
+		&self,
+		from: Hash,
+		to: Option<Hash>,
+		prefix: Option<StorageKey>,
+	) -> FutureResult<Vec<StorageKey>>;
+
 	/// Returns proof of storage entries at a specific block's state.
 	#[rpc(name = "state_getReadProof")]
 	fn read_proof(&self, keys: Vec<StorageKey>, hash: Option<Hash>) -> FutureResult<ReadProof<Hash>>;
 
+	/// Returns a single proof of storage entries and of child storage entries at a specific
+	/// block's state.
+	///
+	/// `child_requests` pairs the prefixed storage key of a default child trie with the keys
+	/// to prove in it. The whole proof verifies against the block's state root.
+	#[rpc(name = "state_getCombinedReadProof")]
+	fn combined_read_proof(
+		&self,
+		keys: Vec<StorageKey>,
+		child_requests: Vec<(PrefixedStorageKey, Vec<StorageKey>)>,
+		hash: Option<Hash>,
+	) -> FutureResult<ReadProof<Hash>>;
+
+	/// Returns proof of a page of storage entries at a specific block's state.
+	///
+	/// Up to `count` keys are proven, starting at `start_index` within `keys`, which may not
+	/// exceed the number of keys.
+	/// Every page verifies independently against the block's state root, so the
+	/// full proof can be assembled over several calls against the same block.
+	#[rpc(name = "state_getReadProofPaged")]
+	fn read_proof_paged(
+		&self,
+		keys: Vec<StorageKey>,
+		count: u32,
+		start_index: u32,
+		hash: Option<Hash>,
+	) -> FutureResult<ReadProof<Hash>>;
+
 	/// New runtime version subscription
 	#[pubsub(
 		subscription = "state_runtimeVersion",
@@ -126,9 +343,33 @@ pub trait StateApi<Hash> {
 	fn unsubscribe_runtime_version(&self, metadata: Option<Self::Metadata>, id: SubscriptionId) -> RpcResult<bool>;
 
 	/// New storage subscription
+	///
+	/// If `deletions_only` is `true`, only removals of the watched keys are reported
+	/// and blocks that merely add or update values produce no notification.
+	///
+	/// If `coalesce_blocks` is given, the change sets of that many notified blocks are
+	/// merged into a single notification, keeping the latest value of every key.
+	/// At most 256 blocks may be merged.
+	///
+	/// If `heartbeat_ms` is given, a heartbeat is sent whenever no notification has been
+	/// sent for that many milliseconds. Heartbeats are change sets without any changes,
+	/// reported at the best block. Blocks are only notified when they change a watched
+	/// top-level key, so an empty change set is always a heartbeat. `heartbeat_ms` must be
+	/// greater than zero.
+	///
+	/// If `from_block` is given, the changes of the watched keys between that block and
+	/// the best block are replayed (in the same form as `state_queryStorage`) before live
+	/// notifications are sent. Replaying requires explicit `keys` and is considered unsafe.
 	#[pubsub(subscription = "state_storage", subscribe, name = "state_subscribeStorage")]
 	fn subscribe_storage(
-		&self, metadata: Self::Metadata, subscriber: Subscriber<StorageChangeSet<Hash>>, keys: Option<Vec<StorageKey>>
+		&self,
+		metadata: Self::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Hash>>,
+		keys: Option<Vec<StorageKey>>,
+		deletions_only: Option<bool>,
+		coalesce_blocks: Option<u32>,
+		heartbeat_ms: Option<u64>,
+		from_block: Option<Hash>,
 	);
 
 	/// Unsubscribe from storage subscription
@@ -137,6 +378,77 @@ pub trait StateApi<Hash> {
 		&self, metadata: Option<Self::Metadata>, id: SubscriptionId
 	) -> RpcResult<bool>;
 
+	/// New storage subscription following finalized blocks only.
+	///
+	/// The values of the watched keys at the last finalized block are sent first. After that,
+	/// the values are read whenever finality advances, at the new last finalized block, and a
+	/// notification is sent if any of them changed compared to the previous notification.
+	/// Blocks finalized together are not looked at one by one, so a change that is undone
+	/// before the last of them is not reported. Changes of blocks that get reverted are never
+	/// reported.
+	#[pubsub(
+		subscription = "state_finalizedStorage",
+		subscribe,
+		name = "state_subscribeStorageFinalized"
+	)]
+	fn subscribe_finalized_storage(
+		&self,
+		metadata: Self::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Hash>>,
+		keys: Vec<StorageKey>,
+	);
+
+	/// Unsubscribe from finalized storage subscription
+	#[pubsub(
+		subscription = "state_finalizedStorage",
+		unsubscribe,
+		name = "state_unsubscribeStorageFinalized"
+	)]
+	fn unsubscribe_finalized_storage(
+		&self, metadata: Option<Self::Metadata>, id: SubscriptionId
+	) -> RpcResult<bool>;
+
+	/// New subscription to a single storage key.
+	///
+	/// The value of the key at the best block is sent first. After that, at most one
+	/// notification is sent every `debounce_ms` milliseconds, carrying the latest change of
+	/// the key within that window. A `debounce_ms` of `0` sends every change.
+	#[pubsub(subscription = "state_storageKey", subscribe, name = "state_subscribeStorageKey")]
+	fn subscribe_storage_key(
+		&self,
+		metadata: Self::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Hash>>,
+		key: StorageKey,
+		debounce_ms: u64,
+	);
+
+	/// Unsubscribe from single storage key subscription
+	#[pubsub(subscription = "state_storageKey", unsubscribe, name = "state_unsubscribeStorageKey")]
+	fn unsubscribe_storage_key(
+		&self, metadata: Option<Self::Metadata>, id: SubscriptionId
+	) -> RpcResult<bool>;
+
+	/// Export every storage entry at a block, including the entries of default child tries.
+	///
+	/// Entries are sent in batches of at most 1000, all entries of the top-level trie first.
+	/// The subscription produces no more notifications once the export is complete.
+	/// Only one export runs at a time; another one is rejected until it completes or is cancelled.
+	///
+	/// Note: requires the node to run with `--rpc-methods=Unsafe`.
+	#[pubsub(subscription = "state_snapshot", subscribe, name = "state_exportSnapshot")]
+	fn export_snapshot(
+		&self,
+		metadata: Self::Metadata,
+		subscriber: Subscriber<SnapshotBatch>,
+		hash: Option<Hash>,
+	);
+
+	/// Cancel a snapshot export
+	#[pubsub(subscription = "state_snapshot", unsubscribe, name = "state_unsubscribeSnapshot")]
+	fn unsubscribe_snapshot(
+		&self, metadata: Option<Self::Metadata>, id: SubscriptionId
+	) -> RpcResult<bool>;
+
 	/// The `state_traceBlock` RPC provides a way to trace the re-execution of a single
 	/// block, collecting Spans and Events from both the client and the relevant WASM runtime.
 	/// The Spans and Events are conceptually equivalent to those from the [Tracing][1] crate.
@@ -24,15 +24,39 @@ mod state_light;
 #[cfg(test)]
 mod tests;
 
-use std::sync::Arc;
+use std::{
+	collections::{BTreeMap, HashMap, HashSet},
+	convert::TryInto,
+	sync::{Arc, atomic::{AtomicUsize, Ordering}},
+	time::Duration,
+};
+use futures::{future::Either, StreamExt as _};
+use futures_timer::Delay;
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId, manager::SubscriptionManager};
-use rpc::{Result as RpcResult, futures::{Future, future::result}};
-
-use sc_rpc_api::{DenyUnsafe, state::ReadProof};
+use rpc::{Result as RpcResult, futures::{Future, future::{join_all, result}}};
+
+use sc_rpc_api::{
+	DenyUnsafe,
+	state::{
+		CallResult, CallTrace, ChainTips, CompressedBlob, MaybeCompressed, PrefixDiff, PrefixSize, ReadProof,
+		SnapshotBatch, StorageDetails, StorageKeyInfo, StorageWithContext, VerifiableStorage,
+	},
+};
 use sc_client_api::light::{RemoteBlockchain, Fetcher};
-use sp_core::{Bytes, storage::{StorageKey, PrefixedStorageKey, StorageData, StorageChangeSet}};
+use codec::Decode;
+use frame_metadata::{
+	DecodeDifferent, RuntimeMetadata, RuntimeMetadataPrefixed, RuntimeMetadataV13, StorageEntryType,
+	StorageHasher,
+};
+use sp_core::{
+	Bytes,
+	hashing::{blake2_128, blake2_256, twox_128, twox_256, twox_64},
+	storage::{StorageKey, PrefixedStorageKey, StorageData, StorageChangeSet},
+};
 use sp_version::RuntimeVersion;
-use sp_runtime::traits::Block as BlockT;
+use sp_rpc::number::NumberOrHex;
+use sp_runtime::traits::{Block as BlockT, HashFor, NumberFor};
+use sp_state_machine::{read_proof_check, StorageProof};
 
 use sp_api::{Metadata, ProvideRuntimeApi, CallApiAt};
 
@@ -40,6 +64,7 @@ use self::error::{Error, FutureResult};
 
 pub use sc_rpc_api::state::*;
 pub use sc_rpc_api::child_state::*;
+pub use self::state_full::{TimedCalls, TIMED_OUT_CALLS_MAX};
 use sc_client_api::{
 	ExecutorProvider, StorageProvider, BlockchainEvents, Backend, BlockBackend, ProofProvider
 };
@@ -47,12 +72,59 @@ use sp_blockchain::{HeaderMetadata, HeaderBackend};
 
 const STORAGE_KEYS_PAGED_MAX_COUNT: u32 = 1000;
 
+/// Maximal number of values (keys times blocks) returned by `state_getStorageMatrix`.
+const STORAGE_MATRIX_MAX_SIZE: u32 = 1000;
+
+/// Maximal number of keys scanned by `state_diffPrefixes`, under both prefixes together.
+const PREFIX_DIFF_MAX_KEYS: u32 = 1000;
+
+/// Maximal number of storage entries sent in one batch by `state_exportSnapshot`.
+const SNAPSHOT_BATCH_SIZE: usize = 1000;
+
+/// Maximal number of blocks whose change sets `state_subscribeStorage` merges into one notification.
+const COALESCE_BLOCKS_MAX: u32 = 256;
+
+/// Maximal number of `state_exportSnapshot` subscriptions running at the same time.
+const SNAPSHOTS_MAX: usize = 1;
+
+/// Maximal number of block numbers resolved by `state_hashesForNumbers`.
+const HASHES_FOR_NUMBERS_MAX_COUNT: u32 = 1000;
+
+/// Codec of the compressed results of `state_getKeys` and `state_getPairs`.
+pub const COMPRESSION_CODEC: &str = "zstd";
+
+/// Compression level passed to the zstd encoder.
+#[cfg(not(target_os = "unknown"))]
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Whether results can be compressed at all. The zstd encoder is a C library and is not
+/// available to the browser node.
+const COMPRESSION_SUPPORTED: bool = cfg!(not(target_os = "unknown"));
+
 /// State backend API.
 pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 	where
 		Block: BlockT + 'static,
 		Client: Send + Sync + 'static,
 {
+	/// Returns the hash of the current best block.
+	///
+	/// Methods taking `None` for the block resolve it independently on every call, so
+	/// callers that need several reads at the same block should resolve it once here.
+	fn best_hash(&self) -> Block::Hash;
+
+	/// Returns the hash of the last finalized block.
+	fn finalized_hash(&self) -> Block::Hash;
+
+	/// Returns the hash of the genesis block.
+	fn genesis_hash(&self) -> Block::Hash;
+
+	/// Returns whether the given block is finalized.
+	fn is_finalized(&self, block: Block::Hash) -> FutureResult<bool>;
+
+	/// Returns the hashes of the canonical blocks with the given numbers, if known.
+	fn block_hashes(&self, numbers: Vec<NumberFor<Block>>) -> FutureResult<Vec<Option<Block::Hash>>>;
+
 	/// Call runtime method at given block.
 	fn call(
 		&self,
@@ -61,6 +133,14 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 		call_data: Bytes,
 	) -> FutureResult<Bytes>;
 
+	/// Call runtime method at given block, collecting the storage writes it makes.
+	fn call_trace(
+		&self,
+		block: Option<Block::Hash>,
+		method: String,
+		call_data: Bytes,
+	) -> FutureResult<CallTrace>;
+
 	/// Returns the keys with prefix, leave empty to get all the keys.
 	fn storage_keys(
 		&self,
@@ -75,6 +155,12 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 		prefix: StorageKey,
 	) -> FutureResult<Vec<(StorageKey, StorageData)>>;
 
+	/// Returns the prefixed storage keys of all default child tries.
+	fn child_keys(
+		&self,
+		block: Option<Block::Hash>,
+	) -> FutureResult<Vec<PrefixedStorageKey>>;
+
 	/// Returns the keys with prefix with pagination support.
 	fn storage_keys_paged(
 		&self,
@@ -91,6 +177,15 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 		key: StorageKey,
 	) -> FutureResult<Option<StorageData>>;
 
+	/// Returns several storage entries at a specific block's state, in the order of `keys`.
+	///
+	/// The state of the block is resolved once for all the keys.
+	fn storage_values(
+		&self,
+		block: Option<Block::Hash>,
+		keys: Vec<StorageKey>,
+	) -> FutureResult<Vec<Option<StorageData>>>;
+
 	/// Returns the hash of a storage entry at a block's state.
 	fn storage_hash(
 		&self,
@@ -108,12 +203,44 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 		key: StorageKey,
 	) -> FutureResult<Option<u64>>;
 
+	/// Returns the number of keys and the total size of the values with `prefix`.
+	fn prefix_size(
+		&self,
+		block: Option<Block::Hash>,
+		prefix: StorageKey,
+	) -> FutureResult<PrefixSize>;
+
+	/// Compares the entries under `prefix_a` with the entries under `prefix_b`, keyed by the
+	/// part of the key following the prefix.
+	///
+	/// Fails without reading any value if there are more than `PREFIX_DIFF_MAX_KEYS` keys under
+	/// both prefixes together.
+	fn diff_prefixes(
+		&self,
+		block: Option<Block::Hash>,
+		prefix_a: StorageKey,
+		prefix_b: StorageKey,
+	) -> FutureResult<PrefixDiff>;
+
+	/// Returns whether a storage entry exists at a block's state.
+	fn exists(
+		&self,
+		block: Option<Block::Hash>,
+		key: StorageKey,
+	) -> FutureResult<bool>;
+
+	/// Returns the state root of a block.
+	fn state_root(&self, block: Option<Block::Hash>) -> FutureResult<Block::Hash>;
+
 	/// Returns the runtime metadata as an opaque blob.
 	fn metadata(&self, block: Option<Block::Hash>) -> FutureResult<Bytes>;
 
 	/// Get the runtime version.
 	fn runtime_version(&self, block: Option<Block::Hash>) -> FutureResult<RuntimeVersion>;
 
+	/// Get the runtime version at the canonical block with the given number.
+	fn runtime_version_at_number(&self, number: NumberFor<Block>) -> FutureResult<RuntimeVersion>;
+
 	/// Query historical storage entries (by key) starting from a block given as the second parameter.
 	///
 	/// NOTE This first returned result contains the initial state of storage for all keys.
@@ -132,6 +259,17 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 		at: Option<Block::Hash>
 	) -> FutureResult<Vec<StorageChangeSet<Block::Hash>>>;
 
+	/// Returns the keys whose values were changed by the blocks after `from` up to `to`.
+	///
+	/// If `prefix` is given, only keys starting with it are returned. Blocks that are not
+	/// covered by changes tries are executed again.
+	fn changed_keys(
+		&self,
+		from: Block::Hash,
+		to: Option<Block::Hash>,
+		prefix: Option<StorageKey>,
+	) -> FutureResult<Vec<StorageKey>>;
+
 	/// Returns proof of storage entries at a specific block's state.
 	fn read_proof(
 		&self,
@@ -139,6 +277,15 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 		keys: Vec<StorageKey>,
 	) -> FutureResult<ReadProof<Block::Hash>>;
 
+	/// Returns a single proof of storage entries and of child storage entries at a specific
+	/// block's state.
+	fn combined_read_proof(
+		&self,
+		block: Option<Block::Hash>,
+		keys: Vec<StorageKey>,
+		child_requests: Vec<(PrefixedStorageKey, Vec<StorageKey>)>,
+	) -> FutureResult<ReadProof<Block::Hash>>;
+
 	/// New runtime version subscription
 	fn subscribe_runtime_version(
 		&self,
@@ -154,11 +301,21 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 	) -> RpcResult<bool>;
 
 	/// New storage subscription
+	///
+	/// If `deletions_only` is set, only entries whose new value is `None` are reported.
+	/// Change sets of `coalesce_blocks` consecutive notifications are merged into one
+	/// (`0` and `1` disable merging). If `heartbeat` is set, an empty change set is sent
+	/// whenever the subscription has been idle for that long; other notifications are never
+	/// empty.
 	fn subscribe_storage(
 		&self,
 		_meta: crate::Metadata,
 		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
 		keys: Option<Vec<StorageKey>>,
+		deletions_only: bool,
+		coalesce_blocks: u32,
+		heartbeat: Option<Duration>,
+		from_block: Option<Block::Hash>,
 	);
 
 	/// Unsubscribe from storage subscription
@@ -168,6 +325,58 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 		id: SubscriptionId,
 	) -> RpcResult<bool>;
 
+	/// New storage subscription following finalized blocks
+	fn subscribe_finalized_storage(
+		&self,
+		_meta: crate::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
+		keys: Vec<StorageKey>,
+	);
+
+	/// Unsubscribe from finalized storage subscription
+	fn unsubscribe_finalized_storage(
+		&self,
+		_meta: Option<crate::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool>;
+
+	/// New subscription to a single storage key.
+	///
+	/// If `debounce` is set, at most one change is reported per `debounce` interval,
+	/// the latest one received within it.
+	fn subscribe_storage_key(
+		&self,
+		_meta: crate::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
+		key: StorageKey,
+		debounce: Option<Duration>,
+	);
+
+	/// Unsubscribe from single storage key subscription
+	fn unsubscribe_storage_key(
+		&self,
+		_meta: Option<crate::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool>;
+
+	/// Export every storage entry at a block in batches.
+	///
+	/// `permit` is kept until the export completes or is cancelled.
+	fn export_snapshot(
+		&self,
+		_meta: crate::Metadata,
+		subscriber: Subscriber<SnapshotBatch>,
+		block: Option<Block::Hash>,
+		permit: Box<dyn Send>,
+	);
+
+	/// Cancel a snapshot export
+	fn unsubscribe_snapshot(
+		&self,
+		_meta: Option<crate::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool>;
+
 	/// Trace storage changes for block
 	fn trace_block(
 		&self,
@@ -178,10 +387,15 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 }
 
 /// Create new state API that works on full node.
+///
+/// Runtime calls made through `state_call` and `state_callTrace` run through `timed_calls`,
+/// if given. It may be shared by the APIs of several transports, so that the timed out calls
+/// of all of them count against the same limit.
 pub fn new_full<BE, Block: BlockT, Client>(
 	client: Arc<Client>,
 	subscriptions: SubscriptionManager,
 	deny_unsafe: DenyUnsafe,
+	timed_calls: Option<TimedCalls>,
 ) -> (State<Block, Client>, ChildState<Block, Client>)
 	where
 		Block: BlockT + 'static,
@@ -193,10 +407,10 @@ pub fn new_full<BE, Block: BlockT, Client>(
 		Client::Api: Metadata<Block>,
 {
 	let child_backend = Box::new(
-		self::state_full::FullState::new(client.clone(), subscriptions.clone())
+		self::state_full::FullState::new(client.clone(), subscriptions.clone(), timed_calls.clone())
 	);
-	let backend = Box::new(self::state_full::FullState::new(client, subscriptions));
-	(State { backend, deny_unsafe }, ChildState { backend: child_backend })
+	let backend = Box::new(self::state_full::FullState::new(client, subscriptions, timed_calls));
+	(State::from_backend(backend, deny_unsafe), ChildState { backend: child_backend })
 }
 
 /// Create new state API that works on light node.
@@ -229,7 +443,13 @@ pub fn new_light<BE, Block: BlockT, Client, F: Fetcher<Block>>(
 			remote_blockchain,
 			fetcher,
 	));
-	(State { backend, deny_unsafe }, ChildState { backend: child_backend })
+	(State::from_backend(backend, deny_unsafe), ChildState { backend: child_backend })
+}
+
+/// Source of the sync status consulted before serving unsafe and heavy state queries.
+pub trait SyncStatusProvider: Send + Sync {
+	/// Best and target block numbers while the node is major syncing, `None` once it caught up.
+	fn major_sync_status(&self) -> Option<(u64, u64)>;
 }
 
 /// State API with subscriptions support.
@@ -237,6 +457,149 @@ pub struct State<Block, Client> {
 	backend: Box<dyn StateBackend<Block, Client>>,
 	/// Whether to deny unsafe calls
 	deny_unsafe: DenyUnsafe,
+	/// Limit of concurrently processed heavy requests, if any.
+	heavy_requests: Option<RequestLimiter>,
+	/// Limit of concurrently running snapshot exports.
+	snapshots: RequestLimiter,
+	/// Runtime methods that may not be called.
+	forbidden_methods: HashSet<String>,
+	/// Whether results may be returned compressed.
+	compression: bool,
+	/// Sync status to check before serving unsafe and heavy queries, if enabled.
+	sync_status: Option<Arc<dyn SyncStatusProvider>>,
+}
+
+/// Counts requests in flight and rejects new ones once `max` is reached.
+struct RequestLimiter {
+	in_flight: Arc<AtomicUsize>,
+	max: usize,
+}
+
+impl RequestLimiter {
+	fn new(max: usize) -> Self {
+		RequestLimiter { in_flight: Arc::new(AtomicUsize::new(0)), max }
+	}
+
+	fn acquire(&self) -> Result<RequestPermit, Error> {
+		if self.in_flight.fetch_add(1, Ordering::SeqCst) >= self.max {
+			self.in_flight.fetch_sub(1, Ordering::SeqCst);
+			return Err(Error::TooManyRequests { max: self.max as u32 })
+		}
+		Ok(RequestPermit(self.in_flight.clone()))
+	}
+}
+
+/// Slot of a request in flight, released on drop.
+struct RequestPermit(Arc<AtomicUsize>);
+
+impl Drop for RequestPermit {
+	fn drop(&mut self) {
+		self.0.fetch_sub(1, Ordering::SeqCst);
+	}
+}
+
+impl<Block, Client> State<Block, Client>
+	where
+		Block: BlockT + 'static,
+		Client: Send + Sync + 'static,
+{
+	/// Create new state API on top of a custom backend.
+	pub fn from_backend(backend: Box<dyn StateBackend<Block, Client>>, deny_unsafe: DenyUnsafe) -> Self {
+		State {
+			backend,
+			deny_unsafe,
+			heavy_requests: None,
+			snapshots: RequestLimiter::new(SNAPSHOTS_MAX),
+			forbidden_methods: HashSet::new(),
+			compression: COMPRESSION_SUPPORTED,
+			sync_status: None,
+		}
+	}
+
+	/// Limit the number of heavy requests (`state_getKeys`, `state_getPairs`,
+	/// `state_getChildKeys`, `state_getKeysPaged`, `state_getPrefixSize`, `state_diffPrefixes`,
+	/// `state_getStorageMatrix`, `state_queryStorage`, `state_changedKeys`,
+	/// `state_getCombinedReadProof`, `state_getReadProofPaged` and `state_traceBlock`)
+	/// processed at the same time.
+	/// Running `state_exportSnapshot` subscriptions count as heavy requests until they complete,
+	/// on top of the `SNAPSHOTS_MAX` limit that applies to them in any case.
+	///
+	/// Heavy requests made while `max` of them are in flight are rejected with
+	/// `Error::TooManyRequests`. Other requests are not limited.
+	pub fn with_heavy_request_limit(mut self, max: usize) -> Self {
+		self.heavy_requests = Some(RequestLimiter::new(max));
+		self
+	}
+
+	/// Forbid calling the given runtime methods through `state_call` and `state_callTrace`.
+	///
+	/// Calls to them are rejected with `Error::MethodForbidden` before anything is executed.
+	pub fn with_forbidden_methods(mut self, methods: impl IntoIterator<Item = String>) -> Self {
+		self.forbidden_methods.extend(methods);
+		self
+	}
+
+	/// Allow or refuse returning compressed results from `state_getKeys` and `state_getPairs`.
+	///
+	/// Compression is allowed by default, except in the browser node where it is not supported.
+	/// Once refused, requests asking for it are rejected with `Error::InvalidParameter`.
+	pub fn with_compression(mut self, enabled: bool) -> Self {
+		self.compression = enabled && COMPRESSION_SUPPORTED;
+		self
+	}
+
+	/// Reject unsafe and heavy queries with `Error::NodeSyncing` while `sync_status` reports
+	/// that the node is major syncing.
+	///
+	/// Their results would reflect a state far behind the network, so clients are told to retry
+	/// against a synced node instead.
+	pub fn with_sync_status(mut self, sync_status: Arc<dyn SyncStatusProvider>) -> Self {
+		self.sync_status = Some(sync_status);
+		self
+	}
+
+	/// Ensure the node is not major syncing, if that is checked.
+	fn check_synced(&self) -> Result<(), Error> {
+		match self.sync_status.as_ref().and_then(|status| status.major_sync_status()) {
+			Some((best, target)) => Err(Error::NodeSyncing { best, target }),
+			None => Ok(()),
+		}
+	}
+
+	/// Resolve the `compress` parameter of a request.
+	fn should_compress(&self, compress: Option<bool>) -> Result<bool, Error> {
+		let compress = compress.unwrap_or(false);
+		if compress && !self.compression {
+			return Err(Error::InvalidParameter {
+				name: "compress".into(),
+				reason: "compression is disabled on this node".into(),
+			})
+		}
+		Ok(compress)
+	}
+
+	/// Ensure `method` may be called.
+	fn check_method_allowed(&self, method: &str) -> Result<(), Error> {
+		if self.forbidden_methods.contains(method) {
+			return Err(Error::MethodForbidden { method: method.into() })
+		}
+		Ok(())
+	}
+
+	/// Run a heavy request, holding a permit of the heavy request limit until it completes.
+	fn heavy<T: Send + 'static>(&self, request: impl FnOnce() -> FutureResult<T>) -> FutureResult<T> {
+		if let Err(err) = self.check_synced() {
+			return Box::new(result(Err(err)))
+		}
+		let permit = match self.heavy_requests.as_ref().map(RequestLimiter::acquire).transpose() {
+			Ok(permit) => permit,
+			Err(err) => return Box::new(result(Err(err))),
+		};
+		Box::new(request().then(move |res| {
+			drop(permit);
+			res
+		}))
+	}
 }
 
 impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
@@ -246,28 +609,100 @@ impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
 {
 	type Metadata = crate::Metadata;
 
-	fn call(&self, method: String, data: Bytes, block: Option<Block::Hash>) -> FutureResult<Bytes> {
-		self.backend.call(block, method, data)
+	fn call(
+		&self,
+		method: String,
+		data: Bytes,
+		block: Option<Block::Hash>,
+		as_hex: Option<bool>,
+	) -> FutureResult<CallResult> {
+		if let Err(err) = self.check_method_allowed(&method) {
+			return Box::new(result(Err(err)))
+		}
+
+		Box::new(self.backend.call(block, method, data).map(move |bytes| match as_hex {
+			Some(false) => CallResult::Array(bytes.0),
+			_ => CallResult::Hex(bytes),
+		}))
+	}
+
+	fn call_decoded(
+		&self,
+		method: String,
+		data: Bytes,
+		block: Option<Block::Hash>,
+	) -> FutureResult<serde_json::Value> {
+		if let Err(err) = self.check_method_allowed(&method) {
+			return Box::new(result(Err(err)))
+		}
+		let decode = match call_result_decoder(&method) {
+			Some(decode) => decode,
+			None => return Box::new(result(Err(Error::InvalidParameter {
+				name: "name".into(),
+				reason: format!("the result of `{}` cannot be decoded", method),
+			}))),
+		};
+
+		Box::new(self.backend.call(block, method, data).and_then(move |bytes| decode(&bytes.0)))
+	}
+
+	fn call_trace(&self, method: String, data: Bytes, block: Option<Block::Hash>) -> FutureResult<CallTrace> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(result(Err(err.into())))
+		}
+		if let Err(err) = self.check_method_allowed(&method) {
+			return Box::new(result(Err(err)))
+		}
+		if let Err(err) = self.check_synced() {
+			return Box::new(result(Err(err)))
+		}
+
+		self.backend.call_trace(block, method, data)
 	}
 
 	fn storage_keys(
 		&self,
 		key_prefix: StorageKey,
 		block: Option<Block::Hash>,
-	) -> FutureResult<Vec<StorageKey>> {
-		self.backend.storage_keys(block, key_prefix)
+		compress: Option<bool>,
+	) -> FutureResult<MaybeCompressed<Vec<StorageKey>>> {
+		let compress = match self.should_compress(compress) {
+			Ok(compress) => compress,
+			Err(err) => return Box::new(result(Err(err))),
+		};
+
+		Box::new(
+			self.heavy(|| self.backend.storage_keys(block, key_prefix))
+				.and_then(move |keys| maybe_compress(keys, compress))
+		)
 	}
 
 	fn storage_pairs(
 		&self,
 		key_prefix: StorageKey,
 		block: Option<Block::Hash>,
-	) -> FutureResult<Vec<(StorageKey, StorageData)>> {
+		compress: Option<bool>,
+	) -> FutureResult<MaybeCompressed<Vec<(StorageKey, StorageData)>>> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(result(Err(err.into())))
+		}
+		let compress = match self.should_compress(compress) {
+			Ok(compress) => compress,
+			Err(err) => return Box::new(result(Err(err))),
+		};
+
+		Box::new(
+			self.heavy(|| self.backend.storage_pairs(block, key_prefix))
+				.and_then(move |pairs| maybe_compress(pairs, compress))
+		)
+	}
+
+	fn child_keys(&self, block: Option<Block::Hash>) -> FutureResult<Vec<PrefixedStorageKey>> {
 		if let Err(err) = self.deny_unsafe.check_if_safe() {
 			return Box::new(result(Err(err.into())))
 		}
 
-		self.backend.storage_pairs(block, key_prefix)
+		self.heavy(|| self.backend.child_keys(block))
 	}
 
 	fn storage_keys_paged(
@@ -285,13 +720,105 @@ impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
 				}
 			)));
 		}
-		self.backend.storage_keys_paged(block, prefix, count, start_key)
+		self.heavy(|| self.backend.storage_keys_paged(block, prefix, count, start_key))
 	}
 
 	fn storage(&self, key: StorageKey, block: Option<Block::Hash>) -> FutureResult<Option<StorageData>> {
 		self.backend.storage(block, key)
 	}
 
+	fn storage_matrix(
+		&self,
+		keys: Vec<StorageKey>,
+		blocks: Vec<Block::Hash>,
+	) -> FutureResult<Vec<Vec<Option<StorageData>>>> {
+		let size = keys.len().saturating_mul(blocks.len());
+		if size > STORAGE_MATRIX_MAX_SIZE as usize {
+			return Box::new(result(Err(
+				Error::InvalidCount {
+					value: size.min(u32::max_value() as usize) as u32,
+					max: STORAGE_MATRIX_MAX_SIZE,
+				}
+			)));
+		}
+
+		self.heavy(|| Box::new(join_all(blocks.into_iter()
+			.map(|block| self.backend.storage_values(Some(block), keys.clone()))
+			.collect::<Vec<_>>()
+		)))
+	}
+
+	fn storage_with_context(
+		&self,
+		key: StorageKey,
+		block: Option<Block::Hash>,
+	) -> FutureResult<StorageWithContext<Block::Hash>> {
+		let block = block.unwrap_or_else(|| self.backend.best_hash());
+		Box::new(self.backend.storage(Some(block), key)
+			.join(self.backend.state_root(Some(block)))
+			.map(move |(value, state_root)| StorageWithContext { value, block, state_root }))
+	}
+
+	fn storage_verified(
+		&self,
+		key: StorageKey,
+		block: Option<Block::Hash>,
+	) -> FutureResult<VerifiableStorage<Block::Hash>> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(result(Err(err.into())))
+		}
+		if let Err(err) = self.check_synced() {
+			return Box::new(result(Err(err)))
+		}
+
+		let block = block.unwrap_or_else(|| self.backend.best_hash());
+		Box::new(self.backend.storage(Some(block), key.clone())
+			.join3(
+				self.backend.state_root(Some(block)),
+				self.backend.read_proof(Some(block), vec![key]),
+			)
+			.map(move |(value, state_root, proof)| VerifiableStorage {
+				value,
+				block,
+				state_root,
+				proof: proof.proof,
+			}))
+	}
+
+	fn storage_details(
+		&self,
+		key: StorageKey,
+		block: Option<Block::Hash>,
+	) -> FutureResult<StorageDetails<Block::Hash>> {
+		let block = block.unwrap_or_else(|| self.backend.best_hash());
+		Box::new(self.backend.storage(Some(block), key.clone())
+			.join3(
+				self.backend.storage_hash(Some(block), key.clone()),
+				self.backend.storage_size(Some(block), key),
+			)
+			.map(|(value, hash, size)| StorageDetails { value, hash, size }))
+	}
+
+	fn decode_storage_key(
+		&self,
+		key: StorageKey,
+		block: Option<Block::Hash>,
+	) -> FutureResult<Option<StorageKeyInfo>> {
+		Box::new(self.backend.metadata(block)
+			.and_then(move |metadata| decode_storage_key(&metadata, &key)))
+	}
+
+	fn encode_storage_key(
+		&self,
+		pallet: String,
+		item: String,
+		keys: Vec<Bytes>,
+		block: Option<Block::Hash>,
+	) -> FutureResult<Option<StorageKey>> {
+		Box::new(self.backend.metadata(block)
+			.and_then(move |metadata| encode_storage_key(&metadata, &pallet, &item, &keys)))
+	}
+
 	fn storage_hash(&self, key: StorageKey, block: Option<Block::Hash>) -> FutureResult<Option<Block::Hash>> {
 		self.backend.storage_hash(block, key)
 	}
@@ -300,6 +827,73 @@ impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
 		self.backend.storage_size(block, key)
 	}
 
+	fn prefix_size(&self, prefix: StorageKey, block: Option<Block::Hash>) -> FutureResult<PrefixSize> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(result(Err(err.into())))
+		}
+
+		self.heavy(|| self.backend.prefix_size(block, prefix))
+	}
+
+	fn diff_prefixes(
+		&self,
+		prefix_a: StorageKey,
+		prefix_b: StorageKey,
+		block: Option<Block::Hash>,
+	) -> FutureResult<PrefixDiff> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(result(Err(err.into())))
+		}
+
+		self.heavy(|| self.backend.diff_prefixes(block, prefix_a, prefix_b))
+	}
+
+	fn storage_exists(&self, key: StorageKey, block: Option<Block::Hash>) -> FutureResult<bool> {
+		self.backend.exists(block, key)
+	}
+
+	fn chain_tips(&self) -> FutureResult<ChainTips<Block::Hash>> {
+		Box::new(result(Ok(ChainTips {
+			best: self.backend.best_hash(),
+			finalized: self.backend.finalized_hash(),
+		})))
+	}
+
+	fn hashes_for_numbers(&self, numbers: Vec<NumberOrHex>) -> FutureResult<Vec<Option<Block::Hash>>> {
+		if numbers.len() > HASHES_FOR_NUMBERS_MAX_COUNT as usize {
+			return Box::new(result(Err(
+				Error::InvalidCount {
+					value: numbers.len().min(u32::max_value() as usize) as u32,
+					max: HASHES_FOR_NUMBERS_MAX_COUNT,
+				}
+			)));
+		}
+
+		// numbers not fitting into `u32` are above any block the database can hold
+		let numbers = numbers.into_iter()
+			.map(|number| TryInto::<u32>::try_into(number).ok().map(Into::into))
+			.collect::<Vec<Option<NumberFor<Block>>>>();
+		let known = numbers.iter().flatten().cloned().collect();
+		Box::new(self.backend.block_hashes(known).map(move |hashes| {
+			let mut hashes = hashes.into_iter();
+			numbers.into_iter()
+				.map(|number| number.and_then(|_| hashes.next().flatten()))
+				.collect()
+		}))
+	}
+
+	fn is_finalized(&self, block: Block::Hash) -> FutureResult<bool> {
+		self.backend.is_finalized(block)
+	}
+
+	fn genesis_hash(&self) -> FutureResult<Block::Hash> {
+		Box::new(result(Ok(self.backend.genesis_hash())))
+	}
+
+	fn state_root(&self, block: Option<Block::Hash>) -> FutureResult<Block::Hash> {
+		self.backend.state_root(block)
+	}
+
 	fn metadata(&self, block: Option<Block::Hash>) -> FutureResult<Bytes> {
 		self.backend.metadata(block)
 	}
@@ -314,7 +908,7 @@ impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
 			return Box::new(result(Err(err.into())))
 		}
 
-		self.backend.query_storage(from, to, keys)
+		self.heavy(|| self.backend.query_storage(from, to, keys))
 	}
 
 	fn query_storage_at(
@@ -325,27 +919,208 @@ impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
 		self.backend.query_storage_at(keys, at)
 	}
 
+	fn changed_keys(
+		&self,
+		from: Block::Hash,
+		to: Option<Block::Hash>,
+		prefix: Option<StorageKey>,
+	) -> FutureResult<Vec<StorageKey>> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(result(Err(err.into())))
+		}
+
+		self.heavy(|| self.backend.changed_keys(from, to, prefix))
+	}
+
 	fn read_proof(&self, keys: Vec<StorageKey>, block: Option<Block::Hash>) -> FutureResult<ReadProof<Block::Hash>> {
 		self.backend.read_proof(block, keys)
 	}
 
+	fn combined_read_proof(
+		&self,
+		keys: Vec<StorageKey>,
+		child_requests: Vec<(PrefixedStorageKey, Vec<StorageKey>)>,
+		block: Option<Block::Hash>,
+	) -> FutureResult<ReadProof<Block::Hash>> {
+		self.heavy(|| self.backend.combined_read_proof(block, keys, child_requests))
+	}
+
+	fn read_proof_paged(
+		&self,
+		keys: Vec<StorageKey>,
+		count: u32,
+		start_index: u32,
+		block: Option<Block::Hash>,
+	) -> FutureResult<ReadProof<Block::Hash>> {
+		if count > STORAGE_KEYS_PAGED_MAX_COUNT {
+			return Box::new(result(Err(
+				Error::InvalidCount {
+					value: count,
+					max: STORAGE_KEYS_PAGED_MAX_COUNT,
+				}
+			)));
+		}
+		// a page starting right after the last key is empty, which ends the paging
+		if start_index as usize > keys.len() {
+			return Box::new(result(Err(Error::InvalidParameter {
+				name: "start_index".into(),
+				reason: format!("exceeds the number of keys ({})", keys.len()),
+			})));
+		}
+		let keys = keys.into_iter()
+			.skip(start_index as usize)
+			.take(count as usize)
+			.collect();
+		self.heavy(|| self.backend.read_proof(block, keys))
+	}
+
 	fn subscribe_storage(
 		&self,
 		meta: Self::Metadata,
 		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
-		keys: Option<Vec<StorageKey>>
+		keys: Option<Vec<StorageKey>>,
+		deletions_only: Option<bool>,
+		coalesce_blocks: Option<u32>,
+		heartbeat_ms: Option<u64>,
+		from_block: Option<Block::Hash>,
 	) {
-		self.backend.subscribe_storage(meta, subscriber, keys);
+		// a zero interval would flood the subscriber with heartbeats
+		if heartbeat_ms == Some(0) {
+			let _ = subscriber.reject(Error::InvalidParameter {
+				name: "heartbeat_ms".into(),
+				reason: "must be greater than zero".into(),
+			}.into());
+			return
+		}
+		if let Some(count) = coalesce_blocks.filter(|count| *count > COALESCE_BLOCKS_MAX) {
+			let _ = subscriber.reject(Error::InvalidParameter {
+				name: "coalesce_blocks".into(),
+				reason: format!("{} exceeds the maximum of {}", count, COALESCE_BLOCKS_MAX),
+			}.into());
+			return
+		}
+		if from_block.is_some() && keys.is_none() {
+			let _ = subscriber.reject(Error::InvalidParameter {
+				name: "from_block".into(),
+				reason: "replaying changes requires explicit keys".into(),
+			}.into());
+			return
+		}
+		if from_block.is_some() {
+			if let Err(err) = self.deny_unsafe.check_if_safe() {
+				let _ = subscriber.reject(err.into());
+				return
+			}
+			if let Err(err) = self.check_synced() {
+				let _ = subscriber.reject(err.into());
+				return
+			}
+		}
+
+		self.backend.subscribe_storage(
+			meta,
+			subscriber,
+			keys,
+			deletions_only.unwrap_or(false),
+			coalesce_blocks.unwrap_or(1),
+			heartbeat_ms.map(Duration::from_millis),
+			from_block,
+		);
 	}
 
 	fn unsubscribe_storage(&self, meta: Option<Self::Metadata>, id: SubscriptionId) -> RpcResult<bool> {
 		self.backend.unsubscribe_storage(meta, id)
 	}
 
+	fn subscribe_finalized_storage(
+		&self,
+		meta: Self::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
+		keys: Vec<StorageKey>,
+	) {
+		self.backend.subscribe_finalized_storage(meta, subscriber, keys);
+	}
+
+	fn unsubscribe_finalized_storage(
+		&self,
+		meta: Option<Self::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool> {
+		self.backend.unsubscribe_finalized_storage(meta, id)
+	}
+
+	fn subscribe_storage_key(
+		&self,
+		meta: Self::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
+		key: StorageKey,
+		debounce_ms: u64,
+	) {
+		let debounce = Some(debounce_ms).filter(|ms| *ms > 0).map(Duration::from_millis);
+		self.backend.subscribe_storage_key(meta, subscriber, key, debounce);
+	}
+
+	fn unsubscribe_storage_key(
+		&self,
+		meta: Option<Self::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool> {
+		self.backend.unsubscribe_storage_key(meta, id)
+	}
+
+	fn export_snapshot(
+		&self,
+		meta: Self::Metadata,
+		subscriber: Subscriber<SnapshotBatch>,
+		block: Option<Block::Hash>,
+	) {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			let _ = subscriber.reject(err.into());
+			return
+		}
+		if let Err(err) = self.check_synced() {
+			let _ = subscriber.reject(err.into());
+			return
+		}
+		let permit = self.snapshots.acquire().and_then(|snapshot| {
+			let heavy = self.heavy_requests.as_ref().map(RequestLimiter::acquire).transpose()?;
+			Ok((snapshot, heavy))
+		});
+		let permit = match permit {
+			Ok(permit) => permit,
+			Err(err) => {
+				let _ = subscriber.reject(err.into());
+				return
+			},
+		};
+
+		self.backend.export_snapshot(meta, subscriber, block, Box::new(permit));
+	}
+
+	fn unsubscribe_snapshot(
+		&self,
+		meta: Option<Self::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool> {
+		self.backend.unsubscribe_snapshot(meta, id)
+	}
+
 	fn runtime_version(&self, at: Option<Block::Hash>) -> FutureResult<RuntimeVersion> {
 		self.backend.runtime_version(at)
 	}
 
+	fn runtime_version_at_number(&self, number: NumberOrHex) -> FutureResult<RuntimeVersion> {
+		let block_num: u32 = match number.try_into() {
+			Ok(block_num) => block_num,
+			Err(_) => return Box::new(result(Err(Error::InvalidParameter {
+				name: "number".into(),
+				reason: format!("`{:?}` > u32::max_value(), the max block number is u32.", number),
+			}))),
+		};
+
+		self.backend.runtime_version_at_number(block_num.into())
+	}
+
 	fn subscribe_runtime_version(&self, meta: Self::Metadata, subscriber: Subscriber<RuntimeVersion>) {
 		self.backend.subscribe_runtime_version(meta, subscriber);
 	}
@@ -372,7 +1147,7 @@ impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
 			return Box::new(result(Err(err.into())))
 		}
 
-		self.backend.trace_block(block, targets, storage_keys)
+		self.heavy(|| self.backend.trace_block(block, targets, storage_keys))
 	}
 }
 
@@ -486,6 +1261,325 @@ impl<Block, Client> ChildStateApi<Block::Hash> for ChildState<Block, Client>
 
 }
 
+/// Verify a `ReadProof` returned by `state_getReadProof` against the given state root.
+///
+/// Returns the proven value (or its absence) of every key in `keys`.
+pub fn verify_read_proof<Block: BlockT>(
+	state_root: Block::Hash,
+	proof: ReadProof<Block::Hash>,
+	keys: &[StorageKey],
+) -> Result<BTreeMap<StorageKey, Option<StorageData>>, Error> {
+	let proof = StorageProof::new(proof.proof.into_iter().map(|node| node.0).collect());
+	read_proof_check::<HashFor<Block>, _>(state_root, proof, keys.iter().map(|key| &key.0))
+		.map(|values| values
+			.into_iter()
+			.map(|(key, value)| (StorageKey(key), value.map(StorageData)))
+			.collect()
+		)
+		.map_err(|e| client_err(sp_blockchain::Error::from_state(e)))
+}
+
+/// Storage item of a pallet, as described by the runtime metadata.
+struct StorageItem {
+	pallet: String,
+	prefix: String,
+	name: String,
+	hashers: Vec<StorageHasher>,
+}
+
+impl StorageItem {
+	/// Key prefix shared by all values of this item.
+	fn key_prefix(&self) -> Vec<u8> {
+		[twox_128(self.prefix.as_bytes()), twox_128(self.name.as_bytes())].concat()
+	}
+}
+
+/// Collect the storage items of all pallets in the SCALE encoded runtime `metadata`.
+fn storage_items(metadata: &[u8]) -> Result<Vec<StorageItem>, Error> {
+	fn decoded<B, O>(value: DecodeDifferent<B, O>) -> Option<O> {
+		match value {
+			DecodeDifferent::Decoded(value) => Some(value),
+			DecodeDifferent::Encode(_) => None,
+		}
+	}
+
+	let metadata = RuntimeMetadataPrefixed::decode(&mut &metadata[..])
+		.map_err(|e| Error::Client(Box::new(e)))?;
+	let modules = match metadata.1 {
+		RuntimeMetadata::V13(RuntimeMetadataV13 { modules, .. }) => decoded(modules).unwrap_or_default(),
+		_ => return Ok(Vec::new()),
+	};
+
+	let mut items = Vec::new();
+	for module in modules {
+		let storage = match module.storage.and_then(decoded) {
+			Some(storage) => storage,
+			None => continue,
+		};
+		let pallet = decoded(module.name).unwrap_or_default();
+		let prefix = decoded(storage.prefix).unwrap_or_default();
+		for entry in decoded(storage.entries).unwrap_or_default() {
+			let hashers = match entry.ty {
+				StorageEntryType::Plain(_) => Vec::new(),
+				StorageEntryType::Map { hasher, .. } => vec![hasher],
+				StorageEntryType::DoubleMap { hasher, key2_hasher, .. } => vec![hasher, key2_hasher],
+				StorageEntryType::NMap { hashers, .. } => decoded(hashers).unwrap_or_default(),
+			};
+			items.push(StorageItem {
+				pallet: pallet.clone(),
+				prefix: prefix.clone(),
+				name: decoded(entry.name).unwrap_or_default(),
+				hashers,
+			});
+		}
+	}
+	Ok(items)
+}
+
+/// Find the storage item `key` belongs to in the SCALE encoded runtime `metadata`.
+///
+/// Returns `None` if no storage item of the runtime matches the key.
+pub fn decode_storage_key(metadata: &[u8], key: &StorageKey) -> Result<Option<StorageKeyInfo>, Error> {
+	Ok(storage_items(metadata)?
+		.into_iter()
+		.find(|item| key.0.starts_with(&item.key_prefix()))
+		.map(|item| StorageKeyInfo {
+			key_hashers: item.hashers.iter().map(|hasher| format!("{:?}", hasher)).collect(),
+			pallet: item.pallet,
+			item: item.name,
+		}))
+}
+
+/// Build the storage key of `item` in `pallet` from the SCALE encoded `keys`, using the
+/// hashers found in the SCALE encoded runtime `metadata`.
+///
+/// Fewer keys than the item has hashers may be given to build a partial key.
+/// Returns `None` if the runtime has no such storage item.
+pub fn encode_storage_key(
+	metadata: &[u8],
+	pallet: &str,
+	item: &str,
+	keys: &[Bytes],
+) -> Result<Option<StorageKey>, Error> {
+	let item = match storage_items(metadata)?
+		.into_iter()
+		.find(|storage_item| storage_item.pallet == pallet && storage_item.name == item)
+	{
+		Some(item) => item,
+		None => return Ok(None),
+	};
+	if keys.len() > item.hashers.len() {
+		return Err(Error::InvalidCount { value: keys.len() as u32, max: item.hashers.len() as u32 })
+	}
+
+	let mut storage_key = item.key_prefix();
+	for (hasher, key) in item.hashers.iter().zip(keys) {
+		match hasher {
+			StorageHasher::Blake2_128 => storage_key.extend_from_slice(&blake2_128(key)),
+			StorageHasher::Blake2_256 => storage_key.extend_from_slice(&blake2_256(key)),
+			StorageHasher::Blake2_128Concat => {
+				storage_key.extend_from_slice(&blake2_128(key));
+				storage_key.extend_from_slice(key);
+			},
+			StorageHasher::Twox128 => storage_key.extend_from_slice(&twox_128(key)),
+			StorageHasher::Twox256 => storage_key.extend_from_slice(&twox_256(key)),
+			StorageHasher::Twox64Concat => {
+				storage_key.extend_from_slice(&twox_64(key));
+				storage_key.extend_from_slice(key);
+			},
+			StorageHasher::Identity => storage_key.extend_from_slice(key),
+		}
+	}
+	Ok(Some(StorageKey(storage_key)))
+}
+
+/// Merges consecutive change sets into one, keeping the latest value of every key.
+///
+/// The merged set is reported at the block of the last change set.
+fn merge_change_sets<Hash>(change_sets: Vec<StorageChangeSet<Hash>>) -> StorageChangeSet<Hash> {
+	let mut block = None;
+	let mut changes: Vec<(StorageKey, Option<StorageData>)> = Vec::new();
+	let mut positions = HashMap::new();
+	for change_set in change_sets {
+		for (key, value) in change_set.changes {
+			match positions.get(&key) {
+				Some(&position) => changes[position].1 = value,
+				None => {
+					positions.insert(key.clone(), changes.len());
+					changes.push((key, value));
+				},
+			}
+		}
+		block = Some(change_set.block);
+	}
+
+	StorageChangeSet {
+		block: block.expect("merged change sets are never empty; qed"),
+		changes,
+	}
+}
+
+/// Interleaves `stream` with items produced by `heartbeat` whenever the stream has been
+/// idle for `interval`.
+///
+/// The returned stream ends when `stream` ends.
+fn with_heartbeat<S, T, H>(
+	stream: S,
+	interval: Duration,
+	heartbeat: H,
+) -> impl futures::Stream<Item = T>
+	where
+		S: futures::Stream<Item = T> + Unpin,
+		H: Fn() -> T,
+{
+	futures::stream::unfold((stream, heartbeat), move |(mut stream, heartbeat)| async move {
+		let next = match futures::future::select(stream.next(), Delay::new(interval)).await {
+			Either::Left((item, _)) => item,
+			Either::Right(_) => Some(heartbeat()),
+		};
+		next.map(|item| (item, (stream, heartbeat)))
+	})
+}
+
+/// Emits at most one item of `stream` per `interval`, the latest one received within it.
+///
+/// The interval starts with the first item received after the previous emission. When
+/// `stream` ends, the pending item is emitted right away and the returned stream ends too.
+fn with_debounce<S, T>(stream: S, interval: Duration) -> impl futures::Stream<Item = T>
+	where
+		S: futures::Stream<Item = T> + Unpin,
+{
+	futures::stream::unfold(Some(stream), move |stream| async move {
+		let mut stream = stream?;
+		let mut latest = stream.next().await?;
+		let mut window = Delay::new(interval);
+		loop {
+			match futures::future::select(stream.next(), &mut window).await {
+				Either::Left((Some(item), _)) => latest = item,
+				Either::Left((None, _)) => return Some((latest, None)),
+				Either::Right(_) => return Some((latest, Some(stream))),
+			}
+		}
+	})
+}
+
+/// Decoder turning the encoded result of a runtime call into JSON.
+type CallResultDecoder = fn(&[u8]) -> Result<serde_json::Value, Error>;
+
+/// Returns the decoder for the result of the runtime `method`, if its result type is known.
+fn call_result_decoder(method: &str) -> Option<CallResultDecoder> {
+	fn to_json<T: codec::Decode + serde::Serialize>(
+		mut bytes: &[u8],
+	) -> Result<serde_json::Value, Error> {
+		let value = T::decode(&mut bytes).map_err(|e| Error::Client(Box::new(e)))?;
+		serde_json::to_value(value).map_err(|e| Error::Client(Box::new(e)))
+	}
+
+	fn metadata_to_json(mut bytes: &[u8]) -> Result<serde_json::Value, Error> {
+		let metadata = Vec::<u8>::decode(&mut bytes).map_err(|e| Error::Client(Box::new(e)))?;
+		to_json::<RuntimeMetadataPrefixed>(&metadata)
+	}
+
+	// the width of integers such as the account index is chosen by the runtime, but they
+	// are fixed width, so the width follows from the length of the result
+	fn unsigned_to_json(bytes: &[u8]) -> Result<serde_json::Value, Error> {
+		match bytes.len() {
+			4 => to_json::<u32>(bytes),
+			8 => to_json::<u64>(bytes),
+			16 => u128::decode(&mut &bytes[..])
+				.map(|value| serde_json::Value::String(value.to_string()))
+				.map_err(|e| Error::Client(Box::new(e))),
+			_ => Err(Error::Client(Box::new(codec::Error::from("unexpected width of an integer result")))),
+		}
+	}
+
+	match method {
+		"Core_version" => Some(to_json::<RuntimeVersion>),
+		"Metadata_metadata" => Some(metadata_to_json),
+		"AccountNonceApi_account_nonce" => Some(unsigned_to_json),
+		"AuraApi_slot_duration" => Some(unsigned_to_json),
+		_ => None,
+	}
+}
+
+/// Wrap `value` as is or, if `compress` is set, as its compressed JSON encoding.
+fn maybe_compress<T: serde::Serialize>(value: T, compress: bool) -> Result<MaybeCompressed<T>, Error> {
+	if !compress {
+		return Ok(MaybeCompressed::Plain(value))
+	}
+
+	let json = serde_json::to_vec(&value).map_err(|e| Error::Client(Box::new(e)))?;
+	Ok(MaybeCompressed::Compressed(CompressedBlob {
+		codec: COMPRESSION_CODEC.into(),
+		data: base64::encode(compress_zstd(&json)?),
+	}))
+}
+
+#[cfg(not(target_os = "unknown"))]
+fn compress_zstd(data: &[u8]) -> Result<Vec<u8>, Error> {
+	zstd::encode_all(data, COMPRESSION_LEVEL).map_err(|e| Error::Client(Box::new(e)))
+}
+
+// never called, compression is refused up front when `COMPRESSION_SUPPORTED` is false
+#[cfg(target_os = "unknown")]
+fn compress_zstd(_: &[u8]) -> Result<Vec<u8>, Error> {
+	Err(Error::InvalidParameter {
+		name: "compress".into(),
+		reason: "compression is not supported by this node".into(),
+	})
+}
+
+/// Compare the storage entries under `prefix_a` with the ones under `prefix_b`.
+fn diff_prefixed_pairs(
+	prefix_a: &StorageKey,
+	pairs_a: Vec<(StorageKey, StorageData)>,
+	prefix_b: &StorageKey,
+	pairs_b: Vec<(StorageKey, StorageData)>,
+) -> PrefixDiff {
+	let strip = |prefix: &StorageKey, pairs: Vec<(StorageKey, StorageData)>| pairs
+		.into_iter()
+		.map(|(key, value)| (StorageKey(key.0[prefix.0.len()..].to_vec()), value))
+		.collect::<BTreeMap<_, _>>();
+	let entries_a = strip(prefix_a, pairs_a);
+	let mut entries_b = strip(prefix_b, pairs_b);
+
+	let mut diff = PrefixDiff { only_in_a: Vec::new(), only_in_b: Vec::new(), differing: Vec::new() };
+	for (key, value_a) in entries_a {
+		match entries_b.remove(&key) {
+			None => diff.only_in_a.push(key),
+			Some(value_b) if value_b != value_a => diff.differing.push(key),
+			Some(_) => {},
+		}
+	}
+	diff.only_in_b = entries_b.into_iter().map(|(key, _)| key).collect();
+	diff
+}
+
+/// Returns the hashes of the canonical blocks with the given numbers, if known to `client`.
+fn block_hashes<Block: BlockT, Client: HeaderBackend<Block>>(
+	client: &Client,
+	numbers: Vec<NumberFor<Block>>,
+) -> Result<Vec<Option<Block::Hash>>, Error> {
+	numbers.into_iter().map(|number| client.hash(number).map_err(client_err)).collect()
+}
+
+/// Returns whether `block` is the last finalized block or one of its ancestors.
+fn is_finalized<Block: BlockT, Client: HeaderBackend<Block>>(
+	client: &Client,
+	block: Block::Hash,
+) -> Result<bool, Error> {
+	let number = client.number(block)
+		.map_err(client_err)?
+		.ok_or_else(|| Error::InvalidParameter {
+			name: "hash".into(),
+			reason: format!("block {} not found", block),
+		})?;
+	if number > client.info().finalized_number {
+		return Ok(false)
+	}
+	Ok(client.hash(number).map_err(client_err)? == Some(block))
+}
+
 fn client_err(err: sp_blockchain::Error) -> Error {
 	Error::Client(Box::new(err))
 }
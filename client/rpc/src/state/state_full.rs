@@ -18,37 +18,68 @@
 
 //! State API backend for full nodes.
 
-use std::collections::{BTreeMap, HashMap};
-use std::sync::Arc;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, mpsc, atomic::{AtomicUsize, Ordering}};
 use std::ops::Range;
+use std::time::Duration;
 use futures::{future, StreamExt as _, TryStreamExt as _};
 use log::warn;
+use parking_lot::Mutex;
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId, manager::SubscriptionManager};
 use rpc::{Result as RpcResult, futures::{stream, Future, Sink, Stream, future::result}};
 
-use sc_rpc_api::state::ReadProof;
+use sc_rpc_api::state::{CallTrace, PrefixDiff, PrefixSize, ReadProof, SnapshotBatch};
 use sp_blockchain::{
 	Result as ClientResult, Error as ClientError, HeaderMetadata, CachedHeaderMetadata,
 	HeaderBackend
 };
 use sp_core::{
-	Bytes, storage::{well_known_keys, StorageKey, StorageData, StorageChangeSet,
+	Bytes, NeverNativeValue, storage::{well_known_keys, StorageKey, StorageData, StorageChangeSet,
 	ChildInfo, ChildType, PrefixedStorageKey},
 };
 use sp_version::RuntimeVersion;
 use sp_runtime::{
-	generic::BlockId, traits::{Block as BlockT, NumberFor, SaturatedConversion, CheckedSub},
+	generic::BlockId,
+	traits::{Block as BlockT, Header as HeaderT, NumberFor, SaturatedConversion, CheckedSub},
 };
 
-use sp_api::{Metadata, ProvideRuntimeApi, CallApiAt};
+use sp_api::{ApiError, Metadata, ProvideRuntimeApi, CallApiAt, InitializeBlock};
+use codec::Encode;
+use sp_state_machine::{OverlayedChanges, StorageProof};
 
-use super::{StateBackend, ChildStateBackend, error::{FutureResult, Error, Result}, client_err};
+use super::{
+	StateBackend, ChildStateBackend, error::{FutureResult, Error, Result}, block_hashes, client_err,
+	diff_prefixed_pairs, is_finalized, merge_change_sets, with_debounce, with_heartbeat,
+	PREFIX_DIFF_MAX_KEYS, SNAPSHOT_BATCH_SIZE,
+};
 use std::marker::PhantomData;
 use sc_client_api::{
 	Backend, BlockBackend, BlockchainEvents, CallExecutor, StorageProvider, ExecutorProvider,
 	ProofProvider
 };
 
+/// Maximal number of blocks that may be walked by a single `state_changedKeys` call.
+const CHANGED_KEYS_MAX_BLOCKS: usize = 256;
+
+/// Maximal number of blocks that `changed_keys` may execute again when they are not covered
+/// by changes tries.
+pub(crate) const CHANGED_KEYS_MAX_EXECUTED_BLOCKS: usize = 16;
+
+/// Maximal number of timed out runtime calls that may still be running before new calls
+/// are refused.
+///
+/// Timed out calls keep running until the runtime returns, so this bounds the threads
+/// that endless calls can pin.
+pub const TIMED_OUT_CALLS_MAX: usize = 4;
+
+/// Maximal number of idle worker threads kept for runtime calls with a timeout.
+const IDLE_CALL_WORKERS_MAX: usize = 4;
+
+/// Maximal number of blocks that may be replayed when a storage subscription starts.
+const SUBSCRIPTION_REPLAY_MAX_BLOCKS: usize = 256;
+
 /// Ranges to query in state_queryStorage.
 struct QueryStorageRange<Block: BlockT> {
 	/// Hashes of all the blocks in the range.
@@ -63,10 +94,134 @@ struct QueryStorageRange<Block: BlockT> {
 	pub filtered_range: Option<Range<usize>>,
 }
 
+/// Runs runtime calls with a timeout, on worker threads shared by every RPC transport of a node.
+///
+/// The runtime can't be interrupted, so a timed out call keeps its worker busy until it returns.
+/// New calls are refused with `Error::TooManyRequests` while `TIMED_OUT_CALLS_MAX` timed out calls
+/// are still running; calls that complete in time are not limited.
+#[derive(Clone)]
+pub struct TimedCalls {
+	inner: Arc<TimedCallsInner>,
+}
+
+struct TimedCallsInner {
+	timeout: Duration,
+	/// Number of calls that timed out and are still running.
+	timed_out: Arc<AtomicUsize>,
+	/// Job senders of the workers waiting for a call.
+	idle: Mutex<Vec<mpsc::Sender<CallJob>>>,
+}
+
+type CallJob = Box<dyn FnOnce() + Send>;
+
+impl TimedCalls {
+	/// Create new runner of runtime calls that gives up on them after `timeout`.
+	pub fn new(timeout: Duration) -> Self {
+		TimedCalls {
+			inner: Arc::new(TimedCallsInner {
+				timeout,
+				timed_out: Arc::new(AtomicUsize::new(0)),
+				idle: Mutex::new(Vec::new()),
+			}),
+		}
+	}
+
+	/// Run `execute` on a worker thread and wait at most `timeout` for its result.
+	fn execute<T: Send + 'static>(
+		&self,
+		execute: impl FnOnce() -> ClientResult<T> + Send + 'static,
+	) -> Result<ClientResult<T>> {
+		let timeout = self.inner.timeout;
+		if self.inner.timed_out.load(Ordering::SeqCst) >= TIMED_OUT_CALLS_MAX {
+			return Err(Error::TooManyRequests { max: TIMED_OUT_CALLS_MAX as u32 })
+		}
+
+		// the result is sent under the lock, so a call either delivers its result or is
+		// counted as timed out until it returns, never both
+		let (sender, receiver) = mpsc::channel();
+		let abandoned = Arc::new(Mutex::new(false));
+		let call_abandoned = abandoned.clone();
+		let timed_out = self.inner.timed_out.clone();
+		self.spawn(Box::new(move || {
+			let result = panic::catch_unwind(AssertUnwindSafe(execute));
+			let abandoned = call_abandoned.lock();
+			if *abandoned {
+				timed_out.fetch_sub(1, Ordering::SeqCst);
+			} else {
+				let _ = sender.send(result);
+			}
+		}))?;
+
+		let result = match receiver.recv_timeout(timeout) {
+			Ok(result) => result,
+			Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(Err(call_panicked())),
+			Err(mpsc::RecvTimeoutError::Timeout) => {
+				let mut abandoned = abandoned.lock();
+				match receiver.try_recv() {
+					Ok(result) => result,
+					Err(_) => {
+						*abandoned = true;
+						self.inner.timed_out.fetch_add(1, Ordering::SeqCst);
+						return Err(Error::CallTimeout { timeout_ms: timeout.as_millis() as u64 })
+					},
+				}
+			},
+		};
+		Ok(result.unwrap_or_else(|_| Err(call_panicked())))
+	}
+
+	/// Hand `job` to an idle worker, or to a new one if none is idle.
+	fn spawn(&self, job: CallJob) -> Result<()> {
+		let idle = self.inner.idle.lock().pop();
+		let job = match idle {
+			Some(worker) => match worker.send(job) {
+				Ok(()) => return Ok(()),
+				Err(mpsc::SendError(job)) => job,
+			},
+			None => job,
+		};
+
+		// workers only keep a weak reference, so they exit once the runner is dropped
+		let inner = Arc::downgrade(&self.inner);
+		std::thread::Builder::new()
+			.name("state-call".into())
+			.spawn(move || {
+				let mut job = job;
+				loop {
+					job();
+					let (sender, receiver) = mpsc::channel();
+					match inner.upgrade() {
+						Some(inner) => {
+							let mut idle = inner.idle.lock();
+							if idle.len() >= IDLE_CALL_WORKERS_MAX {
+								return
+							}
+							idle.push(sender);
+						},
+						None => return,
+					}
+					job = match receiver.recv() {
+						Ok(job) => job,
+						Err(_) => return,
+					};
+				}
+			})
+			.map(|_| ())
+			.map_err(|e| Error::Client(Box::new(e)))
+	}
+}
+
+/// Error of a runtime call whose execution panicked.
+fn call_panicked() -> ClientError {
+	ClientError::Application("runtime call panicked".into())
+}
+
 /// State API backend for full nodes.
 pub struct FullState<BE, Block: BlockT, Client> {
 	client: Arc<Client>,
 	subscriptions: SubscriptionManager,
+	/// Runner of runtime calls with a timeout, `None` if calls are unbounded.
+	timed_calls: Option<TimedCalls>,
 	_phantom: PhantomData<(BE, Block)>
 }
 
@@ -78,8 +233,59 @@ impl<BE, Block: BlockT, Client> FullState<BE, Block, Client>
 		Block: BlockT + 'static,
 {
 	/// Create new state API backend for full nodes.
-	pub fn new(client: Arc<Client>, subscriptions: SubscriptionManager) -> Self {
-		Self { client, subscriptions, _phantom: PhantomData }
+	pub fn new(
+		client: Arc<Client>,
+		subscriptions: SubscriptionManager,
+		timed_calls: Option<TimedCalls>,
+	) -> Self {
+		Self {
+			client,
+			subscriptions,
+			timed_calls,
+			_phantom: PhantomData,
+		}
+	}
+
+	/// Run a runtime call, through `timed_calls` if calls have a timeout.
+	fn execute_with_timeout<T: Send + 'static>(
+		&self,
+		execute: impl FnOnce() -> ClientResult<T> + Send + 'static,
+	) -> Result<ClientResult<T>> {
+		match self.timed_calls {
+			Some(ref timed_calls) => timed_calls.execute(execute),
+			None => Ok(execute()),
+		}
+	}
+
+	/// Returns the keys written by the block `hash`, by executing it again on top of `parent_hash`.
+	fn written_keys(&self, parent_hash: Block::Hash, hash: Block::Hash) -> Result<Vec<StorageKey>> {
+		let block = self.client.block(&BlockId::Hash(hash))
+			.map_err(client_err)?
+			.ok_or_else(|| client_err(ClientError::UnknownBlock(format!("{:?}", hash))))?
+			.block;
+
+		let client = self.client.clone();
+		let execute = move || {
+			let changes = RefCell::new(OverlayedChanges::default());
+			client
+				.executor()
+				.contextual_call::<_, fn(_, _) -> _, NeverNativeValue, fn() -> _>(
+					|| Ok(()),
+					&BlockId::Hash(parent_hash),
+					"Core_execute_block",
+					&block.encode(),
+					&changes,
+					None,
+					InitializeBlock::Skip,
+					client.execution_extensions().strategies().importing.get_manager(),
+					None,
+					&None,
+					None,
+				)
+				.map(|_| changes.borrow().changes().map(|(key, _)| StorageKey(key.clone())).collect())
+		};
+
+		self.execute_with_timeout(execute)?.map_err(|e| self.state_err(parent_hash, e))
 	}
 
 	/// Returns given block hash or best block hash if None is passed.
@@ -87,6 +293,25 @@ impl<BE, Block: BlockT, Client> FullState<BE, Block, Client>
 		Ok(hash.unwrap_or_else(|| self.client.info().best_hash))
 	}
 
+	/// Converts an error of a state query at `block` into an RPC error.
+	///
+	/// Errors about a known block whose state is no longer available are reported as
+	/// `Error::StatePruned`, so that clients may retry against an archive node.
+	fn state_err(&self, block: Block::Hash, err: ClientError) -> Error {
+		let unknown_block = match err {
+			ClientError::UnknownBlock(_) => true,
+			// runtime API calls wrap the error of the state lookup
+			ClientError::RuntimeApiError(ApiError::Application(ref e)) =>
+				matches!(e.downcast_ref::<ClientError>(), Some(ClientError::UnknownBlock(_))),
+			_ => false,
+		};
+		if unknown_block && matches!(self.client.header(BlockId::Hash(block)), Ok(Some(_))) {
+			Error::StatePruned { hash: format!("{:?}", block) }
+		} else {
+			client_err(err)
+		}
+	}
+
 	/// Splits the `query_storage` block range into 'filtered' and 'unfiltered' subranges.
 	/// Blocks that contain changes within filtered subrange could be filtered using changes tries.
 	/// Blocks that contain changes within unfiltered subrange must be filtered manually.
@@ -175,6 +400,38 @@ impl<BE, Block: BlockT, Client> FullState<BE, Block, Client>
 		Ok(())
 	}
 
+	/// Collects the changes of `keys` from `from` up to the best block, to be sent
+	/// ahead of live notifications when a storage subscription starts.
+	fn replay_storage_changes(
+		&self,
+		from: Block::Hash,
+		keys: &[StorageKey],
+		deletions_only: bool,
+	) -> Result<Vec<StorageChangeSet<Block::Hash>>> {
+		let range = self.split_query_storage_range(from, None)?;
+		if range.hashes.len() > SUBSCRIPTION_REPLAY_MAX_BLOCKS {
+			return Err(invalid_block::<Block>(
+				from,
+				None,
+				format!("replay exceeds maximum of {} blocks", SUBSCRIPTION_REPLAY_MAX_BLOCKS),
+			))
+		}
+
+		let mut changes = Vec::new();
+		let mut last_values = HashMap::new();
+		self.query_storage_unfiltered(&range, keys, &mut last_values, &mut changes)?;
+		self.query_storage_filtered(&range, keys, &last_values, &mut changes)?;
+		if deletions_only {
+			// values at `from` are the starting point, not deletions
+			changes.retain(|set| set.block != from);
+			for set in &mut changes {
+				set.changes.retain(|(_, value)| value.is_none());
+			}
+			changes.retain(|set| !set.changes.is_empty());
+		}
+		Ok(changes)
+	}
+
 	/// Iterates through all blocks that are changing keys within range.filtered_range and collects these changes.
 	fn query_storage_filtered(
 		&self,
@@ -233,26 +490,98 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 		+ Send + Sync + 'static,
 	Client::Api: Metadata<Block>,
 {
+	fn best_hash(&self) -> Block::Hash {
+		self.client.info().best_hash
+	}
+
+	fn finalized_hash(&self) -> Block::Hash {
+		self.client.info().finalized_hash
+	}
+
+	fn genesis_hash(&self) -> Block::Hash {
+		self.client.info().genesis_hash
+	}
+
+	fn is_finalized(&self, block: Block::Hash) -> FutureResult<bool> {
+		Box::new(result(is_finalized::<Block, _>(&*self.client, block)))
+	}
+
+	fn block_hashes(&self, numbers: Vec<NumberFor<Block>>) -> FutureResult<Vec<Option<Block::Hash>>> {
+		Box::new(result(block_hashes::<Block, _>(&*self.client, numbers)))
+	}
+
 	fn call(
 		&self,
 		block: Option<Block::Hash>,
 		method: String,
 		call_data: Bytes,
 	) -> FutureResult<Bytes> {
-		let r = self.block_or_best(block)
-			.and_then(|block| self
-				.client
+		let block = match self.block_or_best(block) {
+			Ok(b) => b,
+			Err(e) => return Box::new(result(Err(client_err(e)))),
+		};
+
+		let client = self.client.clone();
+		let execute = move || client
+			.executor()
+			.call(
+				&BlockId::Hash(block),
+				&method,
+				&*call_data,
+				client.execution_extensions().strategies().other,
+				None,
+			);
+
+		let r = match self.execute_with_timeout(execute) {
+			Ok(r) => r,
+			Err(e) => return Box::new(result(Err(e))),
+		};
+		Box::new(result(r.map(Into::into).map_err(|e| self.state_err(block, e))))
+	}
+
+	fn call_trace(
+		&self,
+		block: Option<Block::Hash>,
+		method: String,
+		call_data: Bytes,
+	) -> FutureResult<CallTrace> {
+		let block = match self.block_or_best(block) {
+			Ok(b) => b,
+			Err(e) => return Box::new(result(Err(client_err(e)))),
+		};
+
+		let client = self.client.clone();
+		let execute = move || {
+			let changes = RefCell::new(OverlayedChanges::default());
+			client
 				.executor()
-				.call(
+				.contextual_call::<_, fn(_, _) -> _, NeverNativeValue, fn() -> _>(
+					|| Ok(()),
 					&BlockId::Hash(block),
 					&method,
 					&*call_data,
-					self.client.execution_extensions().strategies().other,
+					&changes,
+					None,
+					InitializeBlock::Skip,
+					client.execution_extensions().strategies().other.get_manager(),
+					None,
+					&None,
 					None,
 				)
-				.map(Into::into)
-			).map_err(client_err);
-		Box::new(result(r))
+				.map(|r| CallTrace {
+					result: r.into_encoded().into(),
+					changes: changes.borrow()
+						.changes()
+						.map(|(key, value)| (StorageKey(key.clone()), value.value().cloned().map(StorageData)))
+						.collect(),
+				})
+		};
+
+		let r = match self.execute_with_timeout(execute) {
+			Ok(r) => r,
+			Err(e) => return Box::new(result(Err(e))),
+		};
+		Box::new(result(r.map_err(|e| self.state_err(block, e))))
 	}
 
 	fn storage_keys(
@@ -260,10 +589,14 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 		block: Option<Block::Hash>,
 		prefix: StorageKey,
 	) -> FutureResult<Vec<StorageKey>> {
+		let block = match self.block_or_best(block) {
+			Ok(b) => b,
+			Err(e) => return Box::new(result(Err(client_err(e)))),
+		};
+
 		Box::new(result(
-			self.block_or_best(block)
-				.and_then(|block| self.client.storage_keys(&BlockId::Hash(block), &prefix))
-				.map_err(client_err)))
+			self.client.storage_keys(&BlockId::Hash(block), &prefix)
+				.map_err(|e| self.state_err(block, e))))
 	}
 
 	fn storage_pairs(
@@ -271,10 +604,30 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 		block: Option<Block::Hash>,
 		prefix: StorageKey,
 	) -> FutureResult<Vec<(StorageKey, StorageData)>> {
+		let block = match self.block_or_best(block) {
+			Ok(b) => b,
+			Err(e) => return Box::new(result(Err(client_err(e)))),
+		};
+
+		Box::new(result(
+			self.client.storage_pairs(&BlockId::Hash(block), &prefix)
+				.map_err(|e| self.state_err(block, e))))
+	}
+
+	fn child_keys(
+		&self,
+		block: Option<Block::Hash>,
+	) -> FutureResult<Vec<PrefixedStorageKey>> {
+		let block = match self.block_or_best(block) {
+			Ok(b) => b,
+			Err(e) => return Box::new(result(Err(client_err(e)))),
+		};
+
+		let prefix = StorageKey(well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX.to_vec());
 		Box::new(result(
-			self.block_or_best(block)
-				.and_then(|block| self.client.storage_pairs(&BlockId::Hash(block), &prefix))
-				.map_err(client_err)))
+			self.client.storage_keys(&BlockId::Hash(block), &prefix)
+				.map(|keys| keys.into_iter().map(|key| PrefixedStorageKey::new(key.0)).collect())
+				.map_err(|e| self.state_err(block, e))))
 	}
 
 	fn storage_keys_paged(
@@ -284,15 +637,15 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 		count: u32,
 		start_key: Option<StorageKey>,
 	) -> FutureResult<Vec<StorageKey>> {
+		let block = match self.block_or_best(block) {
+			Ok(b) => b,
+			Err(e) => return Box::new(result(Err(client_err(e)))),
+		};
+
 		Box::new(result(
-			self.block_or_best(block)
-				.and_then(|block|
-					self.client.storage_keys_iter(
-						&BlockId::Hash(block), prefix.as_ref(), start_key.as_ref()
-					)
-				)
+			self.client.storage_keys_iter(&BlockId::Hash(block), prefix.as_ref(), start_key.as_ref())
 				.map(|v| v.take(count as usize).collect())
-				.map_err(client_err)))
+				.map_err(|e| self.state_err(block, e))))
 	}
 
 	fn storage(
@@ -300,10 +653,29 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 		block: Option<Block::Hash>,
 		key: StorageKey,
 	) -> FutureResult<Option<StorageData>> {
+		let block = match self.block_or_best(block) {
+			Ok(b) => b,
+			Err(e) => return Box::new(result(Err(client_err(e)))),
+		};
+
+		Box::new(result(
+			self.client.storage(&BlockId::Hash(block), &key)
+				.map_err(|e| self.state_err(block, e))))
+	}
+
+	fn storage_values(
+		&self,
+		block: Option<Block::Hash>,
+		keys: Vec<StorageKey>,
+	) -> FutureResult<Vec<Option<StorageData>>> {
+		let block = match self.block_or_best(block) {
+			Ok(b) => b,
+			Err(e) => return Box::new(result(Err(client_err(e)))),
+		};
+
 		Box::new(result(
-			self.block_or_best(block)
-				.and_then(|block| self.client.storage(&BlockId::Hash(block), &key))
-				.map_err(client_err)))
+			self.client.storage_values(&BlockId::Hash(block), &keys)
+				.map_err(|e| self.state_err(block, e))))
 	}
 
 	fn storage_size(
@@ -318,7 +690,7 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 
 		match self.client.storage(&BlockId::Hash(block), &key) {
 			Ok(Some(d)) => return Box::new(result(Ok(Some(d.0.len() as u64)))),
-			Err(e) => return Box::new(result(Err(client_err(e)))),
+			Err(e) => return Box::new(result(Err(self.state_err(block, e)))),
 			Ok(None) => {},
 		}
 
@@ -332,41 +704,153 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 						None
 					}
 				})
-				.map_err(client_err)
+				.map_err(|e| self.state_err(block, e))
 		))
 	}
 
+	fn prefix_size(
+		&self,
+		block: Option<Block::Hash>,
+		prefix: StorageKey,
+	) -> FutureResult<PrefixSize> {
+		let block = match self.block_or_best(block) {
+			Ok(b) => b,
+			Err(e) => return Box::new(result(Err(client_err(e)))),
+		};
+
+		// values are read one at a time, only their sizes are kept; the iterator starts after
+		// `prefix`, so an entry under the prefix itself is looked up separately
+		let id = BlockId::Hash(block);
+		let call_fn = move || -> ClientResult<PrefixSize> {
+			let mut size = PrefixSize { keys: 0, bytes: 0 };
+			let keys = self.client.storage_keys_iter(&id, Some(&prefix), None)?;
+			for key in std::iter::once(prefix.clone()).chain(keys) {
+				if let Some(value) = self.client.storage(&id, &key)? {
+					size.keys += 1;
+					size.bytes += value.0.len() as u64;
+				}
+			}
+			Ok(size)
+		};
+
+		Box::new(result(call_fn().map_err(|e| self.state_err(block, e))))
+	}
+
+	fn diff_prefixes(
+		&self,
+		block: Option<Block::Hash>,
+		prefix_a: StorageKey,
+		prefix_b: StorageKey,
+	) -> FutureResult<PrefixDiff> {
+		let block = match self.block_or_best(block) {
+			Ok(b) => b,
+			Err(e) => return Box::new(result(Err(client_err(e)))),
+		};
+		let id = BlockId::Hash(block);
+
+		// one key past the limit is enough to reject the request, so the keys are walked lazily
+		// and no value is read before both prefixes are known to fit
+		let keys = |prefix: &StorageKey| self.client.storage_keys_iter(&id, Some(prefix), None)
+			.map(|keys| keys.take(PREFIX_DIFF_MAX_KEYS as usize + 1).collect::<Vec<_>>())
+			.map_err(|e| self.state_err(block, e));
+		let pairs = |keys: Vec<StorageKey>| keys.into_iter()
+			.filter_map(|key| match self.client.storage(&id, &key) {
+				Ok(value) => value.map(|value| Ok((key, value))),
+				Err(e) => Some(Err(self.state_err(block, e))),
+			})
+			.collect::<Result<Vec<_>>>();
+
+		let call_fn = move || {
+			let keys_a = keys(&prefix_a)?;
+			let keys_b = keys(&prefix_b)?;
+			let scanned = keys_a.len() + keys_b.len();
+			if scanned > PREFIX_DIFF_MAX_KEYS as usize {
+				return Err(Error::InvalidCount { value: scanned as u32, max: PREFIX_DIFF_MAX_KEYS })
+			}
+			Ok(diff_prefixed_pairs(&prefix_a, pairs(keys_a)?, &prefix_b, pairs(keys_b)?))
+		};
+
+		Box::new(result(call_fn()))
+	}
+
 	fn storage_hash(
 		&self,
 		block: Option<Block::Hash>,
 		key: StorageKey,
 	) -> FutureResult<Option<Block::Hash>> {
+		let block = match self.block_or_best(block) {
+			Ok(b) => b,
+			Err(e) => return Box::new(result(Err(client_err(e)))),
+		};
+
 		Box::new(result(
-			self.block_or_best(block)
-				.and_then(|block| self.client.storage_hash(&BlockId::Hash(block), &key))
-				.map_err(client_err)))
+			self.client.storage_hash(&BlockId::Hash(block), &key)
+				.map_err(|e| self.state_err(block, e))))
+	}
+
+	fn exists(
+		&self,
+		block: Option<Block::Hash>,
+		key: StorageKey,
+	) -> FutureResult<bool> {
+		let block = match self.block_or_best(block) {
+			Ok(b) => b,
+			Err(e) => return Box::new(result(Err(client_err(e)))),
+		};
+
+		Box::new(result(
+			self.client.storage_hash(&BlockId::Hash(block), &key)
+				.map(|hash| hash.is_some())
+				.map_err(|e| self.state_err(block, e))))
+	}
+
+	fn state_root(&self, block: Option<Block::Hash>) -> FutureResult<Block::Hash> {
+		let block = match self.block_or_best(block) {
+			Ok(b) => b,
+			Err(e) => return Box::new(result(Err(client_err(e)))),
+		};
+
+		Box::new(result(
+			self.client.header(BlockId::Hash(block))
+				.and_then(|header| header
+					.map(|header| *header.state_root())
+					.ok_or_else(|| ClientError::UnknownBlock(format!("{}", block)))
+				)
+				.map_err(|e| self.state_err(block, e))))
 	}
 
 	fn metadata(&self, block: Option<Block::Hash>) -> FutureResult<Bytes> {
+		let block = match self.block_or_best(block) {
+			Ok(b) => b,
+			Err(e) => return Box::new(result(Err(client_err(e)))),
+		};
+
 		Box::new(result(
-			self.block_or_best(block)
-				.map_err(client_err)
-				.and_then(|block|
-					self.client.runtime_api().metadata(&BlockId::Hash(block))
-						.map(Into::into)
-						.map_err(|e| Error::Client(Box::new(e))))
-		))
+			self.client.runtime_api().metadata(&BlockId::Hash(block))
+				.map(Into::into)
+				.map_err(|e| self.state_err(block, e.into()))))
 	}
 
 	fn runtime_version(&self, block: Option<Block::Hash>) -> FutureResult<RuntimeVersion> {
+		let block = match self.block_or_best(block) {
+			Ok(b) => b,
+			Err(e) => return Box::new(result(Err(client_err(e)))),
+		};
+
 		Box::new(result(
-			self.block_or_best(block)
-				.map_err(client_err)
-				.and_then(|block|
-					self.client.runtime_version_at(&BlockId::Hash(block))
-						.map_err(|e| Error::Client(Box::new(e)))
-				)
-		))
+			self.client.runtime_version_at(&BlockId::Hash(block))
+				.map_err(|e| self.state_err(block, e))))
+	}
+
+	fn runtime_version_at_number(&self, number: NumberFor<Block>) -> FutureResult<RuntimeVersion> {
+		match self.client.hash(number) {
+			Ok(Some(hash)) => self.runtime_version(Some(hash)),
+			Ok(None) => Box::new(result(Err(Error::InvalidParameter {
+				name: "number".into(),
+				reason: format!("block {} not found", number),
+			}))),
+			Err(e) => Box::new(result(Err(client_err(e)))),
+		}
 	}
 
 	fn query_storage(
@@ -395,26 +879,121 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 		self.query_storage(at, Some(at), keys)
 	}
 
+	fn changed_keys(
+		&self,
+		from: Block::Hash,
+		to: Option<Block::Hash>,
+		prefix: Option<StorageKey>,
+	) -> FutureResult<Vec<StorageKey>> {
+		let call_fn = move || {
+			let range = self.split_query_storage_range(from, to)?;
+			if range.hashes.len() > CHANGED_KEYS_MAX_BLOCKS {
+				return Err(invalid_block::<Block>(
+					from,
+					to,
+					format!("range exceeds maximum of {} blocks", CHANGED_KEYS_MAX_BLOCKS),
+				))
+			}
+			// blocks that are not covered by changes tries are executed again on top of their
+			// parent to learn which keys they wrote
+			let executed_end = range.filtered_range.as_ref()
+				.map(|filtered| filtered.start)
+				.unwrap_or_else(|| range.hashes.len());
+			if executed_end > CHANGED_KEYS_MAX_EXECUTED_BLOCKS + 1 {
+				return Err(invalid_block::<Block>(
+					from,
+					to,
+					format!(
+						"range exceeds maximum of {} blocks without changes tries",
+						CHANGED_KEYS_MAX_EXECUTED_BLOCKS,
+					),
+				))
+			}
+
+			let prefix = prefix.unwrap_or_else(|| StorageKey(Vec::new()));
+			let mut changed = BTreeSet::new();
+			let blocks = range.hashes.iter().cloned().zip(range.hashes.iter().cloned().skip(1));
+			for (index, (parent_hash, block_hash)) in blocks.enumerate() {
+				let written = if index + 1 < executed_end {
+					self.written_keys(parent_hash, block_hash)?
+				} else {
+					self.client.changed_keys(&BlockId::Hash(block_hash), Some(&prefix)).map_err(client_err)?
+				};
+
+				for key in written {
+					if !key.0.starts_with(&prefix.0) || changed.contains(&key) {
+						continue;
+					}
+					// keys may be written with the value they already had
+					let previous = self.client.storage(&BlockId::Hash(parent_hash), &key)
+						.map_err(|e| self.state_err(parent_hash, e))?;
+					let current = self.client.storage(&BlockId::Hash(block_hash), &key)
+						.map_err(|e| self.state_err(block_hash, e))?;
+					if previous != current {
+						changed.insert(key);
+					}
+				}
+			}
+			Ok(changed.into_iter().collect())
+		};
+		Box::new(result(call_fn()))
+	}
+
 	fn read_proof(
 		&self,
 		block: Option<Block::Hash>,
 		keys: Vec<StorageKey>,
 	) -> FutureResult<ReadProof<Block::Hash>> {
+		let block = match self.block_or_best(block) {
+			Ok(b) => b,
+			Err(e) => return Box::new(result(Err(client_err(e)))),
+		};
+
 		Box::new(result(
-			self.block_or_best(block)
-				.and_then(|block| {
-					self.client
-						.read_proof(
-							&BlockId::Hash(block),
-							&mut keys.iter().map(|key| key.0.as_ref()),
-						)
-						.map(|proof| proof.iter_nodes().map(|node| node.into()).collect())
-						.map(|proof| ReadProof { at: block, proof })
-				})
-				.map_err(client_err),
+			self.client
+				.read_proof(
+					&BlockId::Hash(block),
+					&mut keys.iter().map(|key| key.0.as_ref()),
+				)
+				.map(|proof| proof.iter_nodes().map(|node| node.into()).collect())
+				.map(|proof| ReadProof { at: block, proof })
+				.map_err(|e| self.state_err(block, e)),
 		))
 	}
 
+	fn combined_read_proof(
+		&self,
+		block: Option<Block::Hash>,
+		keys: Vec<StorageKey>,
+		child_requests: Vec<(PrefixedStorageKey, Vec<StorageKey>)>,
+	) -> FutureResult<ReadProof<Block::Hash>> {
+		let block = match self.block_or_best(block) {
+			Ok(b) => b,
+			Err(e) => return Box::new(result(Err(client_err(e)))),
+		};
+		let id = BlockId::Hash(block);
+
+		let mut proofs = Vec::with_capacity(child_requests.len() + 1);
+		let top = self.client.read_proof(&id, &mut keys.iter().map(|key| key.0.as_ref()));
+		match top {
+			Ok(proof) => proofs.push(proof),
+			Err(e) => return Box::new(result(Err(self.state_err(block, e)))),
+		}
+		for (storage_key, keys) in child_requests {
+			let child_info = match child_info("child_requests", &storage_key) {
+				Ok(child_info) => child_info,
+				Err(e) => return Box::new(result(Err(e))),
+			};
+			match self.client.read_child_proof(&id, &child_info, &mut keys.iter().map(|key| key.0.as_ref())) {
+				Ok(proof) => proofs.push(proof),
+				Err(e) => return Box::new(result(Err(self.state_err(block, e)))),
+			}
+		}
+
+		let proof = StorageProof::merge(proofs).iter_nodes().map(|node| node.into()).collect();
+		Box::new(result(Ok(ReadProof { at: block, proof })))
+	}
+
 	fn subscribe_runtime_version(
 		&self,
 		_meta: crate::Metadata,
@@ -479,6 +1058,10 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 		_meta: crate::Metadata,
 		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
 		keys: Option<Vec<StorageKey>>,
+		deletions_only: bool,
+		coalesce_blocks: u32,
+		heartbeat: Option<Duration>,
+		from_block: Option<Block::Hash>,
 	) {
 		let keys = Into::<Option<Vec<_>>>::into(keys);
 		let stream = match self.client.storage_changes_notification_stream(
@@ -492,30 +1075,70 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 			},
 		};
 
-		// initial values
-		let initial = stream::iter_result(keys
-			.map(|keys| {
-				let block = self.client.info().best_hash;
-				let changes = keys
-					.into_iter()
-					.map(|key| StateBackend::storage(self, Some(block.clone()).into(), key.clone())
-						.map(|val| (key.clone(), val))
-						.wait()
-						.unwrap_or_else(|_| (key, None))
-					)
-					.collect();
-				vec![Ok(Ok(StorageChangeSet { block, changes }))]
-			}).unwrap_or_default());
+		// initial values (there's nothing deleted yet when only deletions are watched),
+		// or the changes since `from_block` when a replay is requested
+		let initial = match from_block {
+			Some(from) => match self.replay_storage_changes(
+				from,
+				// the state API only replays explicit keys
+				keys.as_ref().map(|x| &**x).unwrap_or_default(),
+				deletions_only,
+			) {
+				Ok(changes) => changes,
+				Err(err) => {
+					let _ = subscriber.reject(err.into());
+					return;
+				},
+			},
+			None => keys
+				.filter(|keys| !deletions_only && !keys.is_empty())
+				.map(|keys| {
+					let block = self.client.info().best_hash;
+					let changes = keys
+						.into_iter()
+						.map(|key| StateBackend::storage(self, Some(block.clone()).into(), key.clone())
+							.map(|val| (key.clone(), val))
+							.wait()
+							.unwrap_or_else(|_| (key, None))
+						)
+						.collect();
+					vec![StorageChangeSet { block, changes }]
+				}).unwrap_or_default(),
+		};
+		let initial = stream::iter_result(initial.into_iter().map(|changes| Ok(Ok(changes))));
 
 		self.subscriptions.add(subscriber, |sink| {
 			let stream = stream
-				.map(|(block, changes)| Ok::<_, ()>(Ok(StorageChangeSet {
-					block,
-					changes: changes.iter()
+				.filter_map(move |(block, changes)| {
+					let changes = changes.iter()
 						.filter_map(|(o_sk, k, v)| if o_sk.is_none() {
 							Some((k.clone(),v.cloned()))
-						} else { None }).collect(),
-				})))
+						} else { None })
+						.filter(|(_, v)| !deletions_only || v.is_none())
+						.collect::<Vec<_>>();
+					// blocks that only changed child tries leave nothing to report, and an empty
+					// change set is reserved for heartbeats
+					if changes.is_empty() {
+						return future::ready(None);
+					}
+					future::ready(Some(StorageChangeSet { block, changes }))
+				});
+			let stream = match coalesce_blocks {
+				0 | 1 => stream.boxed(),
+				n => stream.chunks(n as usize).map(merge_change_sets).boxed(),
+			};
+			let stream = match heartbeat {
+				Some(interval) => {
+					let client = self.client.clone();
+					with_heartbeat(stream, interval, move || StorageChangeSet {
+						block: client.info().best_hash,
+						changes: Vec::new(),
+					}).boxed()
+				},
+				None => stream,
+			};
+			let stream = stream
+				.map(|changes| Ok::<_, ()>(Ok(changes)))
 				.compat();
 
 			sink
@@ -534,6 +1157,233 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 		Ok(self.subscriptions.cancel(id))
 	}
 
+	fn subscribe_finalized_storage(
+		&self,
+		_meta: crate::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
+		keys: Vec<StorageKey>,
+	) {
+		let client = self.client.clone();
+		let read_values = move |block: Block::Hash| keys
+			.iter()
+			.map(|key| client.storage(&BlockId::Hash(block), key).map(|value| (key.clone(), value)))
+			.collect::<ClientResult<Vec<_>>>();
+
+		let finalized = self.client.info().finalized_hash;
+		let initial = match read_values(finalized) {
+			Ok(initial) => initial,
+			Err(err) => {
+				let _ = subscriber.reject(self.state_err(finalized, err).into());
+				return;
+			},
+		};
+		let mut last_values = initial.iter().cloned().collect::<HashMap<_, _>>();
+		let initial = stream::iter_result(vec![Ok(Ok(StorageChangeSet { block: finalized, changes: initial }))]);
+
+		self.subscriptions.add(subscriber, |sink| {
+			let stream = self.client.finality_notification_stream()
+				.filter_map(move |notification| {
+					// a block whose values can't be read is skipped rather than reported
+					// with made up deletions
+					let values = match read_values(notification.hash) {
+						Ok(values) => values,
+						Err(err) => {
+							warn!("Cannot read storage at finalized block {}: {:?}", notification.hash, err);
+							return future::ready(None);
+						},
+					};
+					let changes = values
+						.into_iter()
+						.filter(|(key, value)| last_values.insert(key.clone(), value.clone()).as_ref() != Some(value))
+						.collect::<Vec<_>>();
+					if changes.is_empty() {
+						return future::ready(None);
+					}
+					future::ready(Some(StorageChangeSet { block: notification.hash, changes }))
+				})
+				.map(|changes| Ok::<_, ()>(Ok(changes)))
+				.compat();
+
+			sink
+				.sink_map_err(|e| warn!("Error sending notifications: {:?}", e))
+				.send_all(initial.chain(stream))
+				// we ignore the resulting Stream (if the first stream is over we are unsubscribed)
+				.map(|_| ())
+		});
+	}
+
+	fn unsubscribe_finalized_storage(
+		&self,
+		_meta: Option<crate::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool> {
+		Ok(self.subscriptions.cancel(id))
+	}
+
+	fn subscribe_storage_key(
+		&self,
+		_meta: crate::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
+		key: StorageKey,
+		debounce: Option<Duration>,
+	) {
+		let stream = match self.client.storage_changes_notification_stream(
+			Some(&[key.clone()][..]),
+			None,
+		) {
+			Ok(stream) => stream,
+			Err(err) => {
+				let _ = subscriber.reject(client_err(err).into());
+				return;
+			},
+		};
+
+		let block = self.client.info().best_hash;
+		let value = match self.client.storage(&BlockId::Hash(block), &key) {
+			Ok(value) => value,
+			Err(err) => {
+				let _ = subscriber.reject(self.state_err(block, err).into());
+				return;
+			},
+		};
+		let initial = stream::iter_result(vec![
+			Ok(Ok(StorageChangeSet { block, changes: vec![(key, value)] })),
+		]);
+
+		self.subscriptions.add(subscriber, |sink| {
+			let stream = stream
+				.filter_map(|(block, changes)| {
+					let change = changes.iter()
+						.filter(|(o_sk, _, _)| o_sk.is_none())
+						.map(|(_, k, v)| (k.clone(), v.cloned()))
+						.last();
+					future::ready(change.map(|change| StorageChangeSet { block, changes: vec![change] }))
+				});
+			let stream = match debounce {
+				Some(interval) => with_debounce(stream, interval).boxed(),
+				None => stream.boxed(),
+			};
+			let stream = stream
+				.map(|changes| Ok::<_, ()>(Ok(changes)))
+				.compat();
+
+			sink
+				.sink_map_err(|e| warn!("Error sending notifications: {:?}", e))
+				.send_all(initial.chain(stream))
+				// we ignore the resulting Stream (if the first stream is over we are unsubscribed)
+				.map(|_| ())
+		});
+	}
+
+	fn unsubscribe_storage_key(
+		&self,
+		_meta: Option<crate::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool> {
+		Ok(self.subscriptions.cancel(id))
+	}
+
+	fn export_snapshot(
+		&self,
+		_meta: crate::Metadata,
+		subscriber: Subscriber<SnapshotBatch>,
+		block: Option<Block::Hash>,
+		permit: Box<dyn Send>,
+	) {
+		let block = match self.block_or_best(block) {
+			Ok(block) => block,
+			Err(err) => {
+				let _ = subscriber.reject(client_err(err).into());
+				return;
+			},
+		};
+		let id = BlockId::Hash(block);
+		let child_prefix = StorageKey(well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX.to_vec());
+		let children = match self.client.storage_keys(&id, &child_prefix) {
+			Ok(keys) => keys.into_iter().map(|key| PrefixedStorageKey::new(key.0)).collect::<Vec<_>>(),
+			Err(err) => {
+				let _ = subscriber.reject(self.state_err(block, err).into());
+				return;
+			},
+		};
+
+		// batches of the top-level trie are read lazily, one page of keys at a time
+		let client = self.client.clone();
+		let mut last_key = None;
+		let top = std::iter::from_fn(move || {
+			let keys = match client.storage_keys_iter(&id, None, last_key.as_ref()) {
+				Ok(keys) => keys.take(SNAPSHOT_BATCH_SIZE).collect::<Vec<_>>(),
+				Err(err) => return Some(Err(client_err(err))),
+			};
+			if keys.is_empty() {
+				return None;
+			}
+			last_key = keys.last().cloned();
+			keys.into_iter()
+				.filter_map(|key| client.storage(&id, &key).map(|value| value.map(|value| (key, value))).transpose())
+				.collect::<ClientResult<Vec<_>>>()
+				.map(|pairs| Some(SnapshotBatch { child: None, pairs }))
+				.map_err(client_err)
+				.transpose()
+		});
+
+		// child tries are paged the same way, one after another
+		let client = self.client.clone();
+		let children = children.into_iter().flat_map(move |storage_key| {
+			let client = client.clone();
+			let child_info = match ChildType::from_prefixed_key(&storage_key) {
+				Some((ChildType::ParentKeyId, key)) => ChildInfo::new_default(key),
+				None => {
+					let err = client_err(ClientError::InvalidChildStorageKey);
+					return Box::new(std::iter::once(Err(err))) as Box<dyn Iterator<Item = _> + Send>
+				},
+			};
+			let mut last_key = None;
+			Box::new(std::iter::from_fn(move || {
+				let keys = match client.child_storage_keys_iter(&id, child_info.clone(), None, last_key.as_ref()) {
+					Ok(keys) => keys.take(SNAPSHOT_BATCH_SIZE).collect::<Vec<_>>(),
+					Err(err) => return Some(Err(client_err(err))),
+				};
+				if keys.is_empty() {
+					return None;
+				}
+				last_key = keys.last().cloned();
+				keys.into_iter()
+					.filter_map(|key| client.child_storage(&id, &child_info, &key)
+						.map(|value| value.map(|value| (key, value)))
+						.transpose())
+					.collect::<ClientResult<Vec<_>>>()
+					.map(|pairs| Some(SnapshotBatch { child: Some(storage_key.clone()), pairs }))
+					.map_err(client_err)
+					.transpose()
+			}))
+		});
+
+		// the export ends with the first error
+		let mut failed = false;
+		let batches = top.chain(children).take_while(move |batch| {
+			let more = !failed;
+			failed = batch.is_err();
+			more
+		});
+		let batches = stream::iter_result(batches.map(|batch| Ok(batch.map_err(Into::into))));
+
+		self.subscriptions.add(subscriber, |sink| {
+			sink
+				.sink_map_err(|e| warn!("Error sending notifications: {:?}", e))
+				.send_all(batches)
+				.map(move |_| drop(permit))
+		});
+	}
+
+	fn unsubscribe_snapshot(
+		&self,
+		_meta: Option<crate::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool> {
+		Ok(self.subscriptions.cancel(id))
+	}
+
 	fn trace_block(
 		&self,
 		block: Block::Hash,
@@ -565,23 +1415,25 @@ impl<BE, Block, Client> ChildStateBackend<Block, Client> for FullState<BE, Block
 		storage_key: PrefixedStorageKey,
 		keys: Vec<StorageKey>,
 	) -> FutureResult<ReadProof<Block::Hash>> {
+		let block = match self.block_or_best(block) {
+			Ok(b) => b,
+			Err(e) => return Box::new(result(Err(client_err(e)))),
+		};
+		let child_info = match child_info("child_storage_key", &storage_key) {
+			Ok(child_info) => child_info,
+			Err(e) => return Box::new(result(Err(e))),
+		};
+
 		Box::new(result(
-			self.block_or_best(block)
-				.and_then(|block| {
-					let child_info = match ChildType::from_prefixed_key(&storage_key) {
-						Some((ChildType::ParentKeyId, storage_key)) => ChildInfo::new_default(storage_key),
-						None => return Err(sp_blockchain::Error::InvalidChildStorageKey),
-					};
-					self.client
-						.read_child_proof(
-							&BlockId::Hash(block),
-							&child_info,
-							&mut keys.iter().map(|key| key.0.as_ref()),
-						)
-						.map(|proof| proof.iter_nodes().map(|node| node.into()).collect())
-						.map(|proof| ReadProof { at: block, proof })
-				})
-				.map_err(client_err),
+			self.client
+				.read_child_proof(
+					&BlockId::Hash(block),
+					&child_info,
+					&mut keys.iter().map(|key| key.0.as_ref()),
+				)
+				.map(|proof| proof.iter_nodes().map(|node| node.into()).collect())
+				.map(|proof| ReadProof { at: block, proof })
+				.map_err(|e| self.state_err(block, e)),
 		))
 	}
 
@@ -591,20 +1443,18 @@ impl<BE, Block, Client> ChildStateBackend<Block, Client> for FullState<BE, Block
 		storage_key: PrefixedStorageKey,
 		prefix: StorageKey,
 	) -> FutureResult<Vec<StorageKey>> {
+		let block = match self.block_or_best(block) {
+			Ok(b) => b,
+			Err(e) => return Box::new(result(Err(client_err(e)))),
+		};
+		let child_info = match child_info("child_storage_key", &storage_key) {
+			Ok(child_info) => child_info,
+			Err(e) => return Box::new(result(Err(e))),
+		};
+
 		Box::new(result(
-			self.block_or_best(block)
-				.and_then(|block| {
-					let child_info = match ChildType::from_prefixed_key(&storage_key) {
-						Some((ChildType::ParentKeyId, storage_key)) => ChildInfo::new_default(storage_key),
-						None => return Err(sp_blockchain::Error::InvalidChildStorageKey),
-					};
-					self.client.child_storage_keys(
-						&BlockId::Hash(block),
-						&child_info,
-						&prefix,
-					)
-				})
-				.map_err(client_err)))
+			self.client.child_storage_keys(&BlockId::Hash(block), &child_info, &prefix)
+				.map_err(|e| self.state_err(block, e))))
 	}
 
 	fn storage(
@@ -613,20 +1463,18 @@ impl<BE, Block, Client> ChildStateBackend<Block, Client> for FullState<BE, Block
 		storage_key: PrefixedStorageKey,
 		key: StorageKey,
 	) -> FutureResult<Option<StorageData>> {
+		let block = match self.block_or_best(block) {
+			Ok(b) => b,
+			Err(e) => return Box::new(result(Err(client_err(e)))),
+		};
+		let child_info = match child_info("child_storage_key", &storage_key) {
+			Ok(child_info) => child_info,
+			Err(e) => return Box::new(result(Err(e))),
+		};
+
 		Box::new(result(
-			self.block_or_best(block)
-				.and_then(|block| {
-					let child_info = match ChildType::from_prefixed_key(&storage_key) {
-						Some((ChildType::ParentKeyId, storage_key)) => ChildInfo::new_default(storage_key),
-						None => return Err(sp_blockchain::Error::InvalidChildStorageKey),
-					};
-					self.client.child_storage(
-						&BlockId::Hash(block),
-						&child_info,
-						&key,
-					)
-				})
-				.map_err(client_err)))
+			self.client.child_storage(&BlockId::Hash(block), &child_info, &key)
+				.map_err(|e| self.state_err(block, e))))
 	}
 
 	fn storage_hash(
@@ -635,20 +1483,18 @@ impl<BE, Block, Client> ChildStateBackend<Block, Client> for FullState<BE, Block
 		storage_key: PrefixedStorageKey,
 		key: StorageKey,
 	) -> FutureResult<Option<Block::Hash>> {
+		let block = match self.block_or_best(block) {
+			Ok(b) => b,
+			Err(e) => return Box::new(result(Err(client_err(e)))),
+		};
+		let child_info = match child_info("child_storage_key", &storage_key) {
+			Ok(child_info) => child_info,
+			Err(e) => return Box::new(result(Err(e))),
+		};
+
 		Box::new(result(
-			self.block_or_best(block)
-				.and_then(|block| {
-					let child_info = match ChildType::from_prefixed_key(&storage_key) {
-						Some((ChildType::ParentKeyId, storage_key)) => ChildInfo::new_default(storage_key),
-						None => return Err(sp_blockchain::Error::InvalidChildStorageKey),
-					};
-					self.client.child_storage_hash(
-						&BlockId::Hash(block),
-						&child_info,
-						&key,
-					)
-				})
-				.map_err(client_err)))
+			self.client.child_storage_hash(&BlockId::Hash(block), &child_info, &key)
+				.map_err(|e| self.state_err(block, e))))
 	}
 }
 
@@ -687,6 +1533,17 @@ fn invalid_block_range<B: BlockT>(
 	}
 }
 
+/// Returns the default child trie of `storage_key`, which is passed as the `name` parameter.
+fn child_info(name: &str, storage_key: &PrefixedStorageKey) -> Result<ChildInfo> {
+	match ChildType::from_prefixed_key(storage_key) {
+		Some((ChildType::ParentKeyId, storage_key)) => Ok(ChildInfo::new_default(storage_key)),
+		None => Err(Error::InvalidParameter {
+			name: name.into(),
+			reason: "not the key of a default child trie".into(),
+		}),
+	}
+}
+
 fn invalid_block<B: BlockT>(
 	from: B::Hash,
 	to: Option<B::Hash>,
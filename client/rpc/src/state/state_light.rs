@@ -21,11 +21,13 @@
 use std::{
 	sync::Arc,
 	collections::{HashSet, HashMap, hash_map::Entry},
+	time::Duration,
 };
 use codec::Decode;
 use futures::{
 	future::{ready, Either},
 	channel::oneshot::{channel, Sender},
+	compat::Stream01CompatExt,
 	FutureExt, TryFutureExt,
 	StreamExt as _, TryStreamExt as _,
 };
@@ -40,7 +42,7 @@ use rpc::{
 	futures::stream::Stream,
 };
 
-use sc_rpc_api::state::ReadProof;
+use sc_rpc_api::state::{CallTrace, PrefixDiff, PrefixSize, ReadProof, SnapshotBatch};
 use sp_blockchain::{Error as ClientError, HeaderBackend};
 use sc_client_api::{
 	BlockchainEvents,
@@ -54,9 +56,12 @@ use sp_core::{
 	storage::{StorageKey, PrefixedStorageKey, StorageData, StorageChangeSet},
 };
 use sp_version::RuntimeVersion;
-use sp_runtime::{generic::BlockId, traits::{Block as BlockT, HashFor}};
+use sp_runtime::{generic::BlockId, traits::{Block as BlockT, Header as HeaderT, HashFor, NumberFor}};
 
-use super::{StateBackend, ChildStateBackend, error::{FutureResult, Error}, client_err};
+use super::{
+	StateBackend, ChildStateBackend, error::{FutureResult, Error}, block_hashes, client_err,
+	is_finalized, merge_change_sets, with_heartbeat,
+};
 
 /// Storage data map of storage keys => (optional) storage value.
 type StorageMap = HashMap<StorageKey, Option<StorageData>>;
@@ -175,6 +180,26 @@ impl<Block, F, Client> StateBackend<Block, Client> for LightState<Block, F, Clie
 		Client: BlockchainEvents<Block> + HeaderBackend<Block> + Send + Sync + 'static,
 		F: Fetcher<Block> + 'static
 {
+	fn best_hash(&self) -> Block::Hash {
+		self.client.info().best_hash
+	}
+
+	fn finalized_hash(&self) -> Block::Hash {
+		self.client.info().finalized_hash
+	}
+
+	fn genesis_hash(&self) -> Block::Hash {
+		self.client.info().genesis_hash
+	}
+
+	fn is_finalized(&self, block: Block::Hash) -> FutureResult<bool> {
+		Box::new(result(is_finalized::<Block, _>(&*self.client, block)))
+	}
+
+	fn block_hashes(&self, numbers: Vec<NumberFor<Block>>) -> FutureResult<Vec<Option<Block::Hash>>> {
+		Box::new(result(block_hashes::<Block, _>(&*self.client, numbers)))
+	}
+
 	fn call(
 		&self,
 		block: Option<Block::Hash>,
@@ -190,6 +215,15 @@ impl<Block, F, Client> StateBackend<Block, Client> for LightState<Block, F, Clie
 		).boxed().compat())
 	}
 
+	fn call_trace(
+		&self,
+		_block: Option<Block::Hash>,
+		_method: String,
+		_call_data: Bytes,
+	) -> FutureResult<CallTrace> {
+		Box::new(result(Err(client_err(ClientError::NotAvailableOnLightClient))))
+	}
+
 	fn storage_keys(
 		&self,
 		_block: Option<Block::Hash>,
@@ -206,6 +240,13 @@ impl<Block, F, Client> StateBackend<Block, Client> for LightState<Block, F, Clie
 		Box::new(result(Err(client_err(ClientError::NotAvailableOnLightClient))))
 	}
 
+	fn child_keys(
+		&self,
+		_block: Option<Block::Hash>,
+	) -> FutureResult<Vec<PrefixedStorageKey>> {
+		Box::new(result(Err(client_err(ClientError::NotAvailableOnLightClient))))
+	}
+
 	fn storage_keys_paged(
 		&self,
 		_block: Option<Block::Hash>,
@@ -224,6 +265,23 @@ impl<Block, F, Client> StateBackend<Block, Client> for LightState<Block, F, Clie
 		Box::new(result(Err(client_err(ClientError::NotAvailableOnLightClient))))
 	}
 
+	fn prefix_size(
+		&self,
+		_: Option<Block::Hash>,
+		_: StorageKey,
+	) -> FutureResult<PrefixSize> {
+		Box::new(result(Err(client_err(ClientError::NotAvailableOnLightClient))))
+	}
+
+	fn diff_prefixes(
+		&self,
+		_: Option<Block::Hash>,
+		_: StorageKey,
+		_: StorageKey,
+	) -> FutureResult<PrefixDiff> {
+		Box::new(result(Err(client_err(ClientError::NotAvailableOnLightClient))))
+	}
+
 	fn storage(
 		&self,
 		block: Option<Block::Hash>,
@@ -240,6 +298,27 @@ impl<Block, F, Client> StateBackend<Block, Client> for LightState<Block, F, Clie
 		))
 	}
 
+	fn storage_values(
+		&self,
+		block: Option<Block::Hash>,
+		keys: Vec<StorageKey>,
+	) -> FutureResult<Vec<Option<StorageData>>> {
+		Box::new(storage(
+			&*self.remote_blockchain,
+			self.fetcher.clone(),
+			self.block_or_best(block),
+			keys.iter().map(|key| key.0.clone()).collect(),
+		).boxed().compat().map(move |values| keys
+			.iter()
+			.map(|key| values
+				.get(key)
+				.cloned()
+				.expect("successful request has entries for all requested keys; qed")
+			)
+			.collect()
+		))
+	}
+
 	fn storage_hash(
 		&self,
 		block: Option<Block::Hash>,
@@ -252,6 +331,22 @@ impl<Block, F, Client> StateBackend<Block, Client> for LightState<Block, F, Clie
 		)
 	}
 
+	fn exists(
+		&self,
+		block: Option<Block::Hash>,
+		key: StorageKey,
+	) -> FutureResult<bool> {
+		// the remote read proof carries the value anyway
+		Box::new(StateBackend::storage(self, block, key).map(|maybe_storage| maybe_storage.is_some()))
+	}
+
+	fn state_root(&self, block: Option<Block::Hash>) -> FutureResult<Block::Hash> {
+		Box::new(resolve_header(&*self.remote_blockchain, &*self.fetcher, self.block_or_best(block))
+			.map_ok(|header| *header.state_root())
+			.boxed()
+			.compat())
+	}
+
 	fn metadata(&self, block: Option<Block::Hash>) -> FutureResult<Bytes> {
 		let metadata = self.call(block, "Metadata_metadata".into(), Bytes(Vec::new()))
 			.and_then(|metadata| OpaqueMetadata::decode(&mut &metadata.0[..])
@@ -272,6 +367,14 @@ impl<Block, F, Client> StateBackend<Block, Client> for LightState<Block, F, Clie
 		).boxed().compat())
 	}
 
+	fn runtime_version_at_number(&self, number: NumberFor<Block>) -> FutureResult<RuntimeVersion> {
+		Box::new(runtime_version_at_number(
+			&*self.remote_blockchain,
+			self.fetcher.clone(),
+			number,
+		).boxed().compat())
+	}
+
 	fn query_storage(
 		&self,
 		_from: Block::Hash,
@@ -289,6 +392,15 @@ impl<Block, F, Client> StateBackend<Block, Client> for LightState<Block, F, Clie
 		Box::new(result(Err(client_err(ClientError::NotAvailableOnLightClient))))
 	}
 
+	fn changed_keys(
+		&self,
+		_from: Block::Hash,
+		_to: Option<Block::Hash>,
+		_prefix: Option<StorageKey>,
+	) -> FutureResult<Vec<StorageKey>> {
+		Box::new(result(Err(client_err(ClientError::NotAvailableOnLightClient))))
+	}
+
 	fn read_proof(
 		&self,
 		_block: Option<Block::Hash>,
@@ -297,12 +409,30 @@ impl<Block, F, Client> StateBackend<Block, Client> for LightState<Block, F, Clie
 		Box::new(result(Err(client_err(ClientError::NotAvailableOnLightClient))))
 	}
 
+	fn combined_read_proof(
+		&self,
+		_block: Option<Block::Hash>,
+		_keys: Vec<StorageKey>,
+		_child_requests: Vec<(PrefixedStorageKey, Vec<StorageKey>)>,
+	) -> FutureResult<ReadProof<Block::Hash>> {
+		Box::new(result(Err(client_err(ClientError::NotAvailableOnLightClient))))
+	}
+
 	fn subscribe_storage(
 		&self,
 		_meta: crate::Metadata,
 		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
-		keys: Option<Vec<StorageKey>>
+		keys: Option<Vec<StorageKey>>,
+		deletions_only: bool,
+		coalesce_blocks: u32,
+		heartbeat: Option<Duration>,
+		from_block: Option<Block::Hash>,
 	) {
+		if deletions_only || from_block.is_some() {
+			let _ = subscriber.reject(client_err(ClientError::NotAvailableOnLightClient).into());
+			return;
+		}
+
 		let keys = match keys {
 			Some(keys) if !keys.is_empty() => keys,
 			_ => {
@@ -373,6 +503,26 @@ impl<Block, F, Client> StateBackend<Block, Client> for LightState<Block, F, Clie
 				}
 			);
 
+			let changes_stream: Box<
+				dyn Stream<Item = StorageChangeSet<Block::Hash>, Error = ()> + Send
+			> = match coalesce_blocks {
+				0 | 1 => Box::new(changes_stream),
+				n => Box::new(changes_stream.chunks(n as usize).map(merge_change_sets)),
+			};
+			let changes_stream: Box<
+				dyn Stream<Item = StorageChangeSet<Block::Hash>, Error = ()> + Send
+			> = match heartbeat {
+				Some(interval) => {
+					let client = self.client.clone();
+					let heartbeat = move || Ok(StorageChangeSet {
+						block: client.info().best_hash,
+						changes: Vec::new(),
+					});
+					Box::new(with_heartbeat(changes_stream.compat(), interval, heartbeat).boxed().compat())
+				},
+				None => changes_stream,
+			};
+
 			sink
 				.sink_map_err(|e| warn!("Error sending notifications: {:?}", e))
 				.send_all(changes_stream.map(|changes| Ok(changes)))
@@ -420,6 +570,59 @@ impl<Block, F, Client> StateBackend<Block, Client> for LightState<Block, F, Clie
 		Ok(true)
 	}
 
+	fn subscribe_finalized_storage(
+		&self,
+		_meta: crate::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
+		_keys: Vec<StorageKey>,
+	) {
+		let _ = subscriber.reject(client_err(ClientError::NotAvailableOnLightClient).into());
+	}
+
+	fn unsubscribe_finalized_storage(
+		&self,
+		_meta: Option<crate::Metadata>,
+		_id: SubscriptionId,
+	) -> RpcResult<bool> {
+		Ok(false)
+	}
+
+	fn subscribe_storage_key(
+		&self,
+		_meta: crate::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
+		_key: StorageKey,
+		_debounce: Option<Duration>,
+	) {
+		let _ = subscriber.reject(client_err(ClientError::NotAvailableOnLightClient).into());
+	}
+
+	fn unsubscribe_storage_key(
+		&self,
+		_meta: Option<crate::Metadata>,
+		_id: SubscriptionId,
+	) -> RpcResult<bool> {
+		Ok(false)
+	}
+
+	fn export_snapshot(
+		&self,
+		_meta: crate::Metadata,
+		subscriber: Subscriber<SnapshotBatch>,
+		_block: Option<Block::Hash>,
+		_permit: Box<dyn Send>,
+	) {
+		let _ = subscriber.reject(client_err(ClientError::NotAvailableOnLightClient).into());
+	}
+
+	fn unsubscribe_snapshot(
+		&self,
+		_meta: Option<crate::Metadata>,
+		_id: SubscriptionId,
+	) -> RpcResult<bool> {
+		Ok(false)
+	}
+
 	fn subscribe_runtime_version(
 		&self,
 		_meta: crate::Metadata,
@@ -582,17 +785,27 @@ fn call<Block: BlockT, F: Fetcher<Block>>(
 ) -> impl std::future::Future<Output = Result<Bytes, Error>> {
 	resolve_header(remote_blockchain, &*fetcher, block)
 		.then(move |result| match result {
-			Ok(header) => Either::Left(fetcher.remote_call(RemoteCallRequest {
-				block,
-				header,
-				method,
-				call_data: call_data.0,
-				retry_count: Default::default(),
-			}).then(|result| ready(result.map(Bytes).map_err(client_err)))),
+			Ok(header) => Either::Left(call_at_header(fetcher, header, method, call_data)),
 			Err(error) => Either::Right(ready(Err(error))),
 		})
 }
 
+/// Call runtime method at the block of given header
+fn call_at_header<Block: BlockT, F: Fetcher<Block>>(
+	fetcher: Arc<F>,
+	header: Block::Header,
+	method: String,
+	call_data: Bytes,
+) -> impl std::future::Future<Output = Result<Bytes, Error>> {
+	fetcher.remote_call(RemoteCallRequest {
+		block: header.hash(),
+		header,
+		method,
+		call_data: call_data.0,
+		retry_count: Default::default(),
+	}).then(|result| ready(result.map(Bytes).map_err(client_err)))
+}
+
 /// Get runtime version at given block.
 fn runtime_version<Block: BlockT, F: Fetcher<Block>>(
 	remote_blockchain: &dyn RemoteBlockchain<Block>,
@@ -606,10 +819,34 @@ fn runtime_version<Block: BlockT, F: Fetcher<Block>>(
 		"Core_version".into(),
 		Bytes(Vec::new()),
 	)
-	.then(|version| ready(version.and_then(|version|
-		Decode::decode(&mut &version.0[..])
-			.map_err(|e| client_err(ClientError::VersionInvalid(e.to_string())))
-	)))
+	.then(|version| ready(version.and_then(|version| decode_runtime_version(&version.0))))
+}
+
+/// Get runtime version at the canonical block with given number.
+fn runtime_version_at_number<Block: BlockT, F: Fetcher<Block>>(
+	remote_blockchain: &dyn RemoteBlockchain<Block>,
+	fetcher: Arc<F>,
+	number: NumberFor<Block>,
+) -> impl std::future::Future<Output = Result<RuntimeVersion, Error>> {
+	future_header(remote_blockchain, &*fetcher, BlockId::Number(number))
+		.then(move |result| match result.map_err(client_err).and_then(|maybe_header|
+			maybe_header.ok_or_else(|| Error::InvalidParameter {
+				name: "number".into(),
+				reason: format!("block {} not found", number),
+			})
+		) {
+			Ok(header) => Either::Left(
+				call_at_header(fetcher, header, "Core_version".into(), Bytes(Vec::new()))
+					.then(|version| ready(version.and_then(|version| decode_runtime_version(&version.0))))
+			),
+			Err(error) => Either::Right(ready(Err(error))),
+		})
+}
+
+/// Decode runtime version returned by the `Core_version` runtime call.
+fn decode_runtime_version(version: &[u8]) -> Result<RuntimeVersion, Error> {
+	Decode::decode(&mut &version[..])
+		.map_err(|e| client_err(ClientError::VersionInvalid(e.to_string())))
 }
 
 /// Get storage value at given key at given block.
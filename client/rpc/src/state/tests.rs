@@ -17,13 +17,13 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use super::*;
-use super::state_full::split_range;
+use super::state_full::{split_range, CHANGED_KEYS_MAX_EXECUTED_BLOCKS};
 use self::error::Error;
 
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 use assert_matches::assert_matches;
 use futures01::stream::Stream;
-use sp_core::{storage::ChildInfo, ChangesTrieConfiguration};
+use sp_core::{storage::{ChildInfo, well_known_keys}, ChangesTrieConfiguration};
 use sp_core::hash::H256;
 use sc_block_builder::BlockBuilderProvider;
 use sp_io::hashing::blake2_256;
@@ -63,6 +63,7 @@ fn should_return_storage() {
 		Arc::new(client),
 		SubscriptionManager::new(Arc::new(TaskExecutor)),
 		DenyUnsafe::No,
+		None,
 	);
 	let key = StorageKey(KEY.to_vec());
 
@@ -94,6 +95,337 @@ fn should_return_storage() {
 	);
 }
 
+#[test]
+fn should_return_prefix_size() {
+	let client = TestClientBuilder::new()
+		.add_extra_storage(b":map:acc1".to_vec(), vec![1, 2])
+		.add_extra_storage(b":map:acc2".to_vec(), vec![1, 2, 3])
+		.add_extra_storage(b":other".to_vec(), vec![1, 2, 3, 4])
+		.build();
+	let client = Arc::new(client);
+	let (api, _child) = new_full(
+		client.clone(),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	assert_eq!(
+		api.prefix_size(StorageKey(b":map".to_vec()), None).wait().unwrap(),
+		PrefixSize { keys: 2, bytes: 2 + 3 },
+	);
+	assert_eq!(
+		api.prefix_size(StorageKey(b":map:acc2".to_vec()), None).wait().unwrap(),
+		PrefixSize { keys: 1, bytes: 3 },
+	);
+	assert_eq!(
+		api.prefix_size(StorageKey(b":missing".to_vec()), None).wait().unwrap(),
+		PrefixSize { keys: 0, bytes: 0 },
+	);
+
+	let (api, _child) = new_full(
+		client,
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::Yes,
+		None,
+	);
+	assert_matches!(
+		api.prefix_size(StorageKey(Vec::new()), None).wait(),
+		Err(Error::UnsafeRpcCalled(_))
+	);
+}
+
+#[test]
+fn should_diff_prefixes() {
+	let client = TestClientBuilder::new()
+		.add_extra_storage(b":a:same".to_vec(), vec![1])
+		.add_extra_storage(b":a:changed".to_vec(), vec![2])
+		.add_extra_storage(b":a:only".to_vec(), vec![3])
+		.add_extra_storage(b":b:same".to_vec(), vec![1])
+		.add_extra_storage(b":b:changed".to_vec(), vec![4])
+		.add_extra_storage(b":b:other".to_vec(), vec![5])
+		.build();
+	let (api, _child) = new_full(
+		Arc::new(client),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	assert_eq!(
+		api.diff_prefixes(StorageKey(b":a:".to_vec()), StorageKey(b":b:".to_vec()), None).wait().unwrap(),
+		PrefixDiff {
+			only_in_a: vec![StorageKey(b"only".to_vec())],
+			only_in_b: vec![StorageKey(b"other".to_vec())],
+			differing: vec![StorageKey(b"changed".to_vec())],
+		},
+	);
+}
+
+#[test]
+fn should_reject_diffing_too_many_keys() {
+	let mut builder = TestClientBuilder::new();
+	for i in 0..1001u32 {
+		let prefix: &[u8] = if i % 2 == 0 { b":a:" } else { b":b:" };
+		builder = builder.add_extra_storage([prefix, &i.to_be_bytes()[..]].concat(), vec![1]);
+	}
+	let (api, _child) = new_full(
+		Arc::new(builder.build()),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	assert_matches!(
+		api.diff_prefixes(StorageKey(b":a:".to_vec()), StorageKey(b":b:".to_vec()), None).wait(),
+		Err(Error::InvalidCount { value: 1001, max: 1000 })
+	);
+}
+
+#[test]
+fn should_return_child_keys() {
+	let first = ChildInfo::new_default(b"first");
+	let second = ChildInfo::new_default(b"second");
+	let client = TestClientBuilder::new()
+		.add_extra_child_storage(&first, b":a".to_vec(), vec![1])
+		.add_extra_child_storage(&second, b":b".to_vec(), vec![2])
+		.build();
+	let (api, _child) = new_full(
+		Arc::new(client),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	let child_keys = api.child_keys(None).wait().unwrap();
+	assert!(child_keys.contains(&first.prefixed_storage_key()));
+	assert!(child_keys.contains(&second.prefixed_storage_key()));
+}
+
+#[test]
+fn should_return_storage_exists() {
+	let client = TestClientBuilder::new()
+		.add_extra_storage(b":mock".to_vec(), vec![42; 1024])
+		.build();
+	let genesis_hash = client.genesis_hash();
+	let (api, _child) = new_full(
+		Arc::new(client),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	assert_eq!(api.storage_exists(StorageKey(b":mock".to_vec()), None).wait().unwrap(), true);
+	assert_eq!(api.storage_exists(StorageKey(b":mock".to_vec()), Some(genesis_hash)).wait().unwrap(), true);
+	assert_eq!(api.storage_exists(StorageKey(b":missing".to_vec()), None).wait().unwrap(), false);
+	// a prefix of existing keys is not an entry itself
+	assert_eq!(api.storage_exists(StorageKey(b":mo".to_vec()), None).wait().unwrap(), false);
+	assert_matches!(
+		api.storage_exists(StorageKey(b":mock".to_vec()), Some(H256::random())).wait(),
+		Err(Error::Client(_))
+	);
+}
+
+#[test]
+fn should_trace_storage_writes_of_call() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let (api, _child) = new_full(
+		client.clone(),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	let trace = api.call_trace("TestAPI_test_storage".into(), Bytes(Vec::new()), None).wait().unwrap();
+	assert!(trace.changes.contains(
+		&(StorageKey(b":read_storage".to_vec()), Some(StorageData(b"test".to_vec())))
+	));
+	// the writes are not committed
+	assert_eq!(api.storage(StorageKey(b":read_storage".to_vec()), None).wait().unwrap(), None);
+
+	let (api, _child) = new_full(
+		client,
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::Yes,
+		None,
+	);
+	assert_matches!(
+		api.call_trace("TestAPI_test_storage".into(), Bytes(Vec::new()), None).wait(),
+		Err(Error::UnsafeRpcCalled(_))
+	);
+}
+
+#[test]
+fn should_reject_forbidden_runtime_calls() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let (api, _child) = new_full(
+		client,
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+	let api = api.with_forbidden_methods(vec!["Core_execute_block".to_string()]);
+
+	assert_matches!(
+		api.call("Core_execute_block".into(), Bytes(Vec::new()), None, None).wait(),
+		Err(Error::MethodForbidden { method }) if method == "Core_execute_block"
+	);
+	assert_matches!(
+		api.call_trace("Core_execute_block".into(), Bytes(Vec::new()), None).wait(),
+		Err(Error::MethodForbidden { .. })
+	);
+	assert_matches!(
+		api.call("Core_version".into(), Bytes(Vec::new()), None, None).wait(),
+		Ok(_)
+	);
+}
+
+#[test]
+fn should_return_storage_matrix() {
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let genesis_hash = client.genesis_hash();
+	let (api, _child) = new_full(
+		client.clone(),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	let mut builder = client.new_block(Default::default()).unwrap();
+	builder.push_storage_change(b":a".to_vec(), Some(vec![1])).unwrap();
+	builder.push_storage_change(b":b".to_vec(), Some(vec![2])).unwrap();
+	let block = builder.build().unwrap().block;
+	let block_hash = block.header.hash();
+	executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+
+	let keys = vec![StorageKey(b":a".to_vec()), StorageKey(b":b".to_vec()), StorageKey(b":c".to_vec())];
+	let blocks = vec![genesis_hash, block_hash];
+	let matrix = api.storage_matrix(keys.clone(), blocks.clone()).wait().unwrap();
+	assert_eq!(matrix.len(), blocks.len());
+	for (row, block) in matrix.iter().zip(&blocks) {
+		let expected = keys.iter()
+			.map(|key| api.storage(key.clone(), Some(*block)).wait().unwrap())
+			.collect::<Vec<_>>();
+		assert_eq!(row, &expected);
+	}
+	assert_eq!(matrix[1][0], Some(StorageData(vec![1])));
+
+	let keys = vec![StorageKey(b":a".to_vec()); STORAGE_MATRIX_MAX_SIZE as usize];
+	assert_matches!(
+		api.storage_matrix(keys, blocks).wait(),
+		Err(Error::InvalidCount { value, max }) if value == 2 * STORAGE_MATRIX_MAX_SIZE
+			&& max == STORAGE_MATRIX_MAX_SIZE
+	);
+}
+
+#[test]
+fn should_return_storage_with_context() {
+	const KEY: &[u8] = b":mock";
+
+	let mut client = Arc::new(TestClientBuilder::new()
+		.add_extra_storage(KEY.to_vec(), vec![42])
+		.build());
+	let genesis_hash = client.genesis_hash();
+	let (api, _child) = new_full(
+		client.clone(),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	let best_hash = block.header.hash();
+	let best_root = block.header.state_root;
+	executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+
+	let key = StorageKey(KEY.to_vec());
+	assert_eq!(
+		api.storage_with_context(key.clone(), None).wait().unwrap(),
+		StorageWithContext {
+			value: Some(StorageData(vec![42])),
+			block: best_hash,
+			state_root: best_root,
+		},
+	);
+
+	let genesis_root = client.header(&BlockId::Hash(genesis_hash)).unwrap().unwrap().state_root;
+	let with_context = api.storage_with_context(key, Some(genesis_hash)).wait().unwrap();
+	assert_eq!(with_context.block, genesis_hash);
+	assert_eq!(with_context.state_root, genesis_root);
+}
+
+#[test]
+fn should_return_verifiable_storage() {
+	const KEY: &[u8] = b":mock";
+
+	let client = Arc::new(TestClientBuilder::new()
+		.add_extra_storage(KEY.to_vec(), vec![42])
+		.build());
+	let genesis_hash = client.genesis_hash();
+	let (api, _child) = new_full(
+		client.clone(),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	let key = StorageKey(KEY.to_vec());
+	let verifiable = api.storage_verified(key.clone(), None).wait().unwrap();
+	assert_eq!(verifiable.value, Some(StorageData(vec![42])));
+	assert_eq!(verifiable.block, genesis_hash);
+
+	let proof = ReadProof { at: verifiable.block, proof: verifiable.proof };
+	let proven = verify_read_proof::<runtime::Block>(verifiable.state_root, proof, &[key.clone()]).unwrap();
+	assert_eq!(proven[&key], verifiable.value);
+
+	let (api, _child) = new_full(
+		client,
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::Yes,
+		None,
+	);
+	assert_matches!(api.storage_verified(key, None).wait(), Err(Error::UnsafeRpcCalled(_)));
+}
+
+#[test]
+fn should_return_storage_details() {
+	const KEY: &[u8] = b":mock";
+
+	let mut client = Arc::new(TestClientBuilder::new()
+		.add_extra_storage(KEY.to_vec(), vec![42, 43])
+		.build());
+	let genesis_hash = client.genesis_hash();
+	let (api, _child) = new_full(
+		client.clone(),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	let mut builder = client.new_block(Default::default()).unwrap();
+	builder.push_storage_change(KEY.to_vec(), Some(vec![1])).unwrap();
+	let block = builder.build().unwrap().block;
+	executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+
+	let key = StorageKey(KEY.to_vec());
+	assert_eq!(
+		api.storage_details(key.clone(), None).wait().unwrap(),
+		StorageDetails {
+			value: Some(StorageData(vec![1])),
+			hash: Some(blake2_256(&[1]).into()),
+			size: Some(1),
+		},
+	);
+	assert_eq!(
+		api.storage_details(key, Some(genesis_hash)).wait().unwrap(),
+		StorageDetails {
+			value: Some(StorageData(vec![42, 43])),
+			hash: Some(blake2_256(&[42, 43]).into()),
+			size: Some(2),
+		},
+	);
+}
+
 #[test]
 fn should_return_child_storage() {
 	let child_info = ChildInfo::new_default(STORAGE_KEY);
@@ -105,6 +437,7 @@ fn should_return_child_storage() {
 		client,
 		SubscriptionManager::new(Arc::new(TaskExecutor)),
 		DenyUnsafe::No,
+		None,
 	);
 	let child_key = prefixed_storage_key();
 	let key = StorageKey(b"key".to_vec());
@@ -137,24 +470,992 @@ fn should_return_child_storage() {
 }
 
 #[test]
-fn should_call_contract() {
-	let client = Arc::new(substrate_test_runtime_client::new());
+fn should_return_paged_read_proof() {
+	let keys = (0u8..5).map(|k| StorageKey(vec![b':', b'p', k])).collect::<Vec<_>>();
+	let client = keys.iter()
+		.fold(TestClientBuilder::new(), |builder, key| builder.add_extra_storage(key.0.clone(), vec![42]))
+		.build();
 	let genesis_hash = client.genesis_hash();
-	let (client, _child) = new_full(
+	let state_root = client.header(&BlockId::Hash(genesis_hash)).unwrap().unwrap().state_root;
+	let (api, _child) = new_full(
+		Arc::new(client),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	assert_matches!(
+		api.read_proof_paged(keys.clone(), STORAGE_KEYS_PAGED_MAX_COUNT + 1, 0, None).wait(),
+		Err(Error::InvalidCount { value, max }) if value == STORAGE_KEYS_PAGED_MAX_COUNT + 1
+			&& max == STORAGE_KEYS_PAGED_MAX_COUNT
+	);
+
+	let mut proven = HashMap::new();
+	for start_index in (0..keys.len() as u32).step_by(2) {
+		let page = api.read_proof_paged(keys.clone(), 2, start_index, Some(genesis_hash)).wait().unwrap();
+		assert_eq!(page.at, genesis_hash);
+
+		let page_keys = keys.iter().skip(start_index as usize).take(2).map(|k| k.0.clone());
+		let proof = sp_state_machine::StorageProof::new(page.proof.into_iter().map(|n| n.0).collect());
+		let values = sp_state_machine::read_proof_check::<sp_runtime::traits::BlakeTwo256, _>(
+			state_root,
+			proof,
+			page_keys,
+		).unwrap();
+		proven.extend(values);
+	}
+
+	assert_eq!(proven.len(), keys.len());
+	assert!(keys.iter().all(|k| proven.get(&k.0) == Some(&Some(vec![42]))));
+}
+
+#[test]
+fn should_name_invalid_parameters() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let (api, child) = new_full(
 		client,
 		SubscriptionManager::new(Arc::new(TaskExecutor)),
 		DenyUnsafe::No,
+		None,
+	);
+
+	let err = api.is_finalized(H256::random()).wait().unwrap_err();
+	assert_matches!(&err, Error::InvalidParameter { name, .. } if name == "hash");
+	let err: rpc::Error = err.into();
+	assert_eq!(err.data.unwrap()["name"], "hash");
+
+	assert_matches!(
+		api.runtime_version_at_number(NumberOrHex::Hex(u64::max_value().into())).wait(),
+		Err(Error::InvalidParameter { name, .. }) if name == "number"
+	);
+	assert_matches!(
+		api.runtime_version_at_number(5u64.into()).wait(),
+		Err(Error::InvalidParameter { name, .. }) if name == "number"
 	);
 
+	let keys = vec![StorageKey(b":a".to_vec()), StorageKey(b":b".to_vec())];
+	assert_matches!(api.read_proof_paged(keys.clone(), 1, 2, None).wait(), Ok(_));
+	let err = api.read_proof_paged(keys, 1, 3, None).wait().unwrap_err();
+	assert_matches!(&err, Error::InvalidParameter { name, .. } if name == "start_index");
+	let err: rpc::Error = err.into();
+	assert_eq!(err.data.unwrap()["name"], "start_index");
+
+	let invalid_child_key = PrefixedStorageKey::new(b":not_a_child".to_vec());
 	assert_matches!(
-		client.call("balanceOf".into(), Bytes(vec![1,2,3]), Some(genesis_hash).into()).wait(),
-		Err(Error::Client(_))
-	)
+		child.storage(invalid_child_key.clone(), StorageKey(b":a".to_vec()), None).wait(),
+		Err(Error::InvalidParameter { name, .. }) if name == "child_storage_key"
+	);
+	assert_matches!(
+		api.combined_read_proof(Vec::new(), vec![(invalid_child_key, Vec::new())], None).wait(),
+		Err(Error::InvalidParameter { name, .. }) if name == "child_requests"
+	);
+
+	let (subscriber, id, _transport) = Subscriber::new_test("test");
+	api.subscribe_storage(Default::default(), subscriber, None, None, Some(u32::max_value()), None, None);
+	let err = executor::block_on(id.compat()).unwrap().unwrap_err();
+	assert_eq!(err.data.unwrap()["name"], "coalesce_blocks");
+}
+
+/// Encoded runtime metadata with a single `System::Account` map.
+fn system_account_metadata() -> Vec<u8> {
+	use codec::Encode;
+	use frame_metadata::{
+		ExtrinsicMetadata, ModuleMetadata, StorageEntryMetadata, StorageEntryModifier, StorageMetadata,
+	};
+
+	let metadata: RuntimeMetadataPrefixed = RuntimeMetadataV13 {
+		modules: DecodeDifferent::Decoded(vec![ModuleMetadata {
+			name: DecodeDifferent::Decoded("System".into()),
+			storage: Some(DecodeDifferent::Decoded(StorageMetadata {
+				prefix: DecodeDifferent::Decoded("System".into()),
+				entries: DecodeDifferent::Decoded(vec![StorageEntryMetadata {
+					name: DecodeDifferent::Decoded("Account".into()),
+					modifier: StorageEntryModifier::Default,
+					ty: StorageEntryType::Map {
+						hasher: StorageHasher::Blake2_128Concat,
+						key: DecodeDifferent::Decoded("T::AccountId".into()),
+						value: DecodeDifferent::Decoded("AccountInfo".into()),
+						unused: false,
+					},
+					default: DecodeDifferent::Decoded(Vec::new()),
+					documentation: DecodeDifferent::Decoded(Vec::new()),
+				}]),
+			})),
+			calls: None,
+			event: None,
+			constants: DecodeDifferent::Decoded(Vec::new()),
+			errors: DecodeDifferent::Decoded(Vec::new()),
+			index: 0,
+		}]),
+		extrinsic: ExtrinsicMetadata { version: 4, signed_extensions: Vec::new() },
+	}.into();
+	metadata.encode()
+}
+
+#[test]
+fn should_decode_storage_key() {
+	let metadata = system_account_metadata();
+
+	let mut account_key = [twox_128(b"System"), twox_128(b"Account")].concat();
+	account_key.extend_from_slice(&[1u8; 48]);
+	assert_eq!(
+		decode_storage_key(&metadata, &StorageKey(account_key)).unwrap(),
+		Some(StorageKeyInfo {
+			pallet: "System".into(),
+			item: "Account".into(),
+			key_hashers: vec!["Blake2_128Concat".into()],
+		}),
+	);
+
+	let unknown_key = [twox_128(b"System"), twox_128(b"Unknown")].concat();
+	assert_eq!(decode_storage_key(&metadata, &StorageKey(unknown_key)).unwrap(), None);
+	assert_eq!(decode_storage_key(&metadata, &StorageKey(b":code".to_vec())).unwrap(), None);
+}
+
+#[test]
+fn should_encode_storage_key() {
+	let metadata = system_account_metadata();
+	let account = Bytes(vec![1u8; 32]);
+
+	let account_key = [
+		&twox_128(b"System")[..],
+		&twox_128(b"Account")[..],
+		&blake2_128(&account)[..],
+		&account[..],
+	].concat();
+	assert_eq!(
+		encode_storage_key(&metadata, "System", "Account", &[account.clone()]).unwrap(),
+		Some(StorageKey(account_key)),
+	);
+
+	// a partial key is the prefix of all values of the item
+	let prefix = [twox_128(b"System"), twox_128(b"Account")].concat();
+	assert_eq!(
+		encode_storage_key(&metadata, "System", "Account", &[]).unwrap(),
+		Some(StorageKey(prefix)),
+	);
+
+	assert_matches!(
+		encode_storage_key(&metadata, "System", "Account", &[account.clone(), account.clone()]),
+		Err(Error::InvalidCount { value: 2, max: 1 })
+	);
+	assert_eq!(encode_storage_key(&metadata, "System", "Unknown", &[account]).unwrap(), None);
+}
+
+#[test]
+fn should_return_combined_read_proof() {
+	const KEY: &[u8] = b":mock";
+
+	let child_info = ChildInfo::new_default(STORAGE_KEY);
+	let client = TestClientBuilder::new()
+		.add_extra_storage(KEY.to_vec(), vec![1])
+		.add_extra_child_storage(&child_info, KEY.to_vec(), vec![2])
+		.build();
+	let genesis_hash = client.genesis_hash();
+	let state_root = client.header(&BlockId::Hash(genesis_hash)).unwrap().unwrap().state_root;
+	let (api, _child) = new_full(
+		Arc::new(client),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	let proof = api.combined_read_proof(
+		vec![StorageKey(KEY.to_vec())],
+		vec![(prefixed_storage_key(), vec![StorageKey(KEY.to_vec())])],
+		None,
+	).wait().unwrap();
+	assert_eq!(proof.at, genesis_hash);
+	let proof = StorageProof::new(proof.proof.into_iter().map(|node| node.0).collect());
+
+	let top = sp_state_machine::read_proof_check::<sp_runtime::traits::BlakeTwo256, _>(
+		state_root,
+		proof.clone(),
+		&[KEY],
+	).unwrap();
+	assert_eq!(top[KEY], Some(vec![1]));
+	let child = sp_state_machine::read_child_proof_check::<sp_runtime::traits::BlakeTwo256, _>(
+		state_root,
+		proof,
+		&child_info,
+		&[KEY],
+	).unwrap();
+	assert_eq!(child[KEY], Some(vec![2]));
+}
+
+#[test]
+fn should_verify_read_proof() {
+	const KEY: &[u8] = b":mock";
+	const VALUE: &[u8] = b"hello world";
+
+	let client = TestClientBuilder::new()
+		.add_extra_storage(KEY.to_vec(), VALUE.to_vec())
+		.build();
+	let genesis_hash = client.genesis_hash();
+	let state_root = client.header(&BlockId::Hash(genesis_hash)).unwrap().unwrap().state_root;
+	let (api, _child) = new_full(
+		Arc::new(client),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+	let keys = vec![StorageKey(KEY.to_vec()), StorageKey(b":missing".to_vec())];
+
+	let proof = api.read_proof(keys.clone(), Some(genesis_hash)).wait().unwrap();
+	let proven = verify_read_proof::<runtime::Block>(state_root, proof, &keys).unwrap();
+	assert_eq!(proven.len(), 2);
+	assert_eq!(proven[&keys[0]], Some(StorageData(VALUE.to_vec())));
+	assert_eq!(proven[&keys[1]], None);
+
+	let mut tampered = api.read_proof(keys.clone(), Some(genesis_hash)).wait().unwrap();
+	for node in tampered.proof.iter_mut() {
+		let last = node.0.len() - 1;
+		node.0[last] ^= 0xff;
+	}
+	assert_matches!(
+		verify_read_proof::<runtime::Block>(state_root, tampered, &keys),
+		Err(Error::Client(_))
+	);
+
+	let proof = api.read_proof(keys.clone(), Some(genesis_hash)).wait().unwrap();
+	assert_matches!(
+		verify_read_proof::<runtime::Block>(H256::random(), proof, &keys),
+		Err(Error::Client(_))
+	);
+}
+
+#[test]
+fn should_round_trip_compressed_keys_and_pairs() {
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let mut builder = client.new_block(Default::default()).unwrap();
+	for i in 0..200u32 {
+		let key = [&b":compressed:"[..], &i.to_be_bytes()[..]].concat();
+		builder.push_storage_change(key, Some(vec![42; 32])).unwrap();
+	}
+	let block = builder.build().unwrap().block;
+	executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+
+	let (api, _child) = new_full(
+		client.clone(),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+	let prefix = StorageKey(b":compressed:".to_vec());
+
+	fn decompress<T: serde::de::DeserializeOwned>(blob: CompressedBlob) -> T {
+		assert_eq!(blob.codec, COMPRESSION_CODEC);
+		let data = base64::decode(blob.data).unwrap();
+		serde_json::from_slice(&zstd::decode_all(&data[..]).unwrap()).unwrap()
+	}
+
+	let keys = match api.storage_keys(prefix.clone(), None, None).wait().unwrap() {
+		MaybeCompressed::Plain(keys) => keys,
+		MaybeCompressed::Compressed(_) => panic!("keys are not compressed by default"),
+	};
+	assert_eq!(keys.len(), 200);
+	match api.storage_keys(prefix.clone(), None, Some(true)).wait().unwrap() {
+		MaybeCompressed::Compressed(blob) => assert_eq!(decompress::<Vec<StorageKey>>(blob), keys),
+		MaybeCompressed::Plain(_) => panic!("keys were asked to be compressed"),
+	}
+
+	let pairs = match api.storage_pairs(prefix.clone(), None, Some(false)).wait().unwrap() {
+		MaybeCompressed::Plain(pairs) => pairs,
+		MaybeCompressed::Compressed(_) => panic!("pairs were asked not to be compressed"),
+	};
+	assert_eq!(pairs.len(), 200);
+	match api.storage_pairs(prefix.clone(), None, Some(true)).wait().unwrap() {
+		MaybeCompressed::Compressed(blob) => {
+			assert_eq!(decompress::<Vec<(StorageKey, StorageData)>>(blob), pairs)
+		},
+		MaybeCompressed::Plain(_) => panic!("pairs were asked to be compressed"),
+	}
+
+	let api = api.with_compression(false);
+	assert_matches!(
+		api.storage_keys(prefix, None, Some(true)).wait(),
+		Err(Error::InvalidParameter { ref name, .. }) if name == "compress"
+	);
+}
+
+#[test]
+fn should_return_call_results_as_hex_or_array() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let (api, _child) = new_full(
+		client,
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+	let call = |as_hex| api.call("Core_version".into(), Bytes(Vec::new()), None, as_hex).wait().unwrap();
+
+	let bytes = match call(None) {
+		CallResult::Hex(bytes) => bytes,
+		result => panic!("Unexpected result: {:?}", result),
+	};
+	assert_eq!(call(Some(true)), CallResult::Hex(bytes.clone()));
+	assert_eq!(call(Some(false)), CallResult::Array(bytes.0.clone()));
+	assert_eq!(serde_json::to_value(call(Some(false))).unwrap(), serde_json::json!(bytes.0));
+}
+
+#[test]
+fn should_decode_known_call_results() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let (api, _child) = new_full(
+		client.clone(),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	let version = api.call_decoded("Core_version".into(), Bytes(Vec::new()), None).wait().unwrap();
+	assert_eq!(version["specName"], "test");
+	assert_eq!(version["specVersion"], runtime::VERSION.spec_version);
+	assert!(version["apis"].is_array());
+
+	let nonce = api.call_decoded(
+		"AccountNonceApi_account_nonce".into(),
+		Bytes(AccountKeyring::Alice.to_raw_public().to_vec()),
+		None,
+	).wait().unwrap();
+	assert_eq!(nonce, serde_json::json!(0));
+
+	assert_matches!(
+		api.call_decoded("TestAPI_balance_of".into(), Bytes(Vec::new()), None).wait(),
+		Err(Error::InvalidParameter { ref name, .. }) if name == "name"
+	);
+	// generating session keys writes to the keystore, which a safe method must not do
+	assert_matches!(
+		api.call_decoded("SessionKeys_generate_session_keys".into(), Bytes(Vec::new()), None).wait(),
+		Err(Error::InvalidParameter { ref name, .. }) if name == "name"
+	);
+}
+
+#[test]
+fn should_return_chain_tips() {
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let (api, _child) = new_full(
+		client.clone(),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	for _ in 0..2 {
+		let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+		executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+	}
+	client.finalize_block(BlockId::number(1), None).unwrap();
+
+	let info = client.info();
+	assert_ne!(info.best_hash, info.finalized_hash);
+	assert_eq!(
+		api.chain_tips().wait().unwrap(),
+		ChainTips { best: info.best_hash, finalized: info.finalized_hash },
+	);
+}
+
+#[test]
+fn should_return_hashes_for_numbers() {
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let (api, _child) = new_full(
+		client.clone(),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	let mut hashes = vec![client.genesis_hash()];
+	for _ in 0..2 {
+		let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+		hashes.push(block.header.hash());
+		executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+	}
+
+	let numbers = vec![2u64, 0, 5, 1, u64::max_value()].into_iter().map(Into::into).collect();
+	assert_eq!(
+		api.hashes_for_numbers(numbers).wait().unwrap(),
+		vec![Some(hashes[2]), Some(hashes[0]), None, Some(hashes[1]), None],
+	);
+
+	let too_many = vec![0u64.into(); HASHES_FOR_NUMBERS_MAX_COUNT as usize + 1];
+	assert_matches!(
+		api.hashes_for_numbers(too_many).wait(),
+		Err(Error::InvalidCount { max: HASHES_FOR_NUMBERS_MAX_COUNT, .. })
+	);
+}
+
+#[test]
+fn should_tell_whether_block_is_finalized() {
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let (api, _child) = new_full(
+		client.clone(),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	let mut hashes = vec![client.genesis_hash()];
+	for _ in 0..3 {
+		let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+		hashes.push(block.header.hash());
+		executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+	}
+	client.finalize_block(BlockId::number(2), None).unwrap();
+
+	assert!(api.is_finalized(hashes[0]).wait().unwrap());
+	assert!(api.is_finalized(hashes[1]).wait().unwrap());
+	assert!(api.is_finalized(hashes[2]).wait().unwrap());
+	assert!(!api.is_finalized(hashes[3]).wait().unwrap());
+	assert_matches!(
+		api.is_finalized(H256::random()).wait(),
+		Err(Error::InvalidParameter { name, .. }) if name == "hash"
+	);
+}
+
+#[test]
+fn should_return_genesis_hash() {
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let (api, _child) = new_full(
+		client.clone(),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+
+	assert_eq!(
+		api.genesis_hash().wait().unwrap(),
+		client.hash(0).unwrap().unwrap(),
+	);
+}
+
+#[test]
+fn should_return_state_root() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let genesis_hash = client.genesis_hash();
+	let header = client.header(&BlockId::Hash(genesis_hash)).unwrap().unwrap();
+	let (api, _child) = new_full(
+		client,
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	assert_eq!(api.state_root(Some(genesis_hash)).wait().unwrap(), header.state_root);
+	assert_eq!(api.state_root(None).wait().unwrap(), header.state_root);
+	assert_matches!(
+		api.state_root(Some(H256::random())).wait(),
+		Err(Error::Client(_))
+	);
+}
+
+#[test]
+fn should_report_pruned_state() {
+	const KEY: &[u8] = b":mock";
+
+	let child_info = ChildInfo::new_default(STORAGE_KEY);
+	let mut client = Arc::new(TestClientBuilder::with_pruning_window(1)
+		.add_extra_storage(KEY.to_vec(), vec![42])
+		.add_extra_child_storage(&child_info, KEY.to_vec(), vec![42])
+		.build());
+	let genesis_hash = client.genesis_hash();
+	let (api, child) = new_full(
+		client.clone(),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	for _ in 0..3 {
+		let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+		executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+	}
+
+	let key = StorageKey(KEY.to_vec());
+	assert_matches!(
+		api.storage(key.clone(), None).wait(),
+		Ok(Some(StorageData(ref d))) if d == &[42]
+	);
+	assert_matches!(
+		api.storage(key.clone(), Some(genesis_hash)).wait(),
+		Err(Error::StatePruned { ref hash }) if hash == &format!("{:?}", genesis_hash)
+	);
+	assert_matches!(
+		api.storage_keys(key.clone(), Some(genesis_hash), None).wait(),
+		Err(Error::StatePruned { .. })
+	);
+	assert_matches!(
+		api.read_proof(vec![key.clone()], Some(genesis_hash)).wait(),
+		Err(Error::StatePruned { .. })
+	);
+	assert_matches!(
+		api.call("Core_version".into(), Bytes(Vec::new()), Some(genesis_hash), None).wait(),
+		Err(Error::StatePruned { .. })
+	);
+	assert_matches!(
+		api.storage_pairs(key.clone(), Some(genesis_hash), None).wait(),
+		Err(Error::StatePruned { .. })
+	);
+	assert_matches!(
+		api.storage_keys_paged(Some(key.clone()), 10, None, Some(genesis_hash)).wait(),
+		Err(Error::StatePruned { .. })
+	);
+	assert_matches!(
+		api.storage_hash(key.clone(), Some(genesis_hash)).wait(),
+		Err(Error::StatePruned { .. })
+	);
+	assert_matches!(
+		api.storage_size(key.clone(), Some(genesis_hash)).wait(),
+		Err(Error::StatePruned { .. })
+	);
+	assert_matches!(api.metadata(Some(genesis_hash)).wait(), Err(Error::StatePruned { .. }));
+	assert_matches!(api.runtime_version(Some(genesis_hash)).wait(), Err(Error::StatePruned { .. }));
+	assert_matches!(
+		child.storage(prefixed_storage_key(), key.clone(), Some(genesis_hash)).wait(),
+		Err(Error::StatePruned { .. })
+	);
+	assert_matches!(
+		child.storage_keys(prefixed_storage_key(), key.clone(), Some(genesis_hash)).wait(),
+		Err(Error::StatePruned { .. })
+	);
+	assert_matches!(
+		child.storage_hash(prefixed_storage_key(), key.clone(), Some(genesis_hash)).wait(),
+		Err(Error::StatePruned { .. })
+	);
+	assert_matches!(
+		child.storage_size(prefixed_storage_key(), key.clone(), Some(genesis_hash)).wait(),
+		Err(Error::StatePruned { .. })
+	);
+	assert_matches!(
+		child.read_child_proof(prefixed_storage_key(), vec![key.clone()], Some(genesis_hash)).wait(),
+		Err(Error::StatePruned { .. })
+	);
+	assert_matches!(
+		api.storage(key, Some(H256::random())).wait(),
+		Err(Error::Client(_))
+	);
+}
+
+#[test]
+fn should_resolve_best_block_on_every_call() {
+	const KEY: &[u8] = b":mock";
+
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let backend = super::state_full::FullState::new(
+		client.clone(),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		None,
+	);
+	let key = StorageKey(KEY.to_vec());
+
+	let pinned = backend.best_hash();
+	assert_eq!(pinned, client.genesis_hash());
+	assert_eq!(StateBackend::storage(&backend, None, key.clone()).wait().unwrap(), None);
+
+	let mut builder = client.new_block(Default::default()).unwrap();
+	builder.push_storage_change(KEY.to_vec(), Some(vec![42])).unwrap();
+	let block = builder.build().unwrap().block;
+	let block_hash = block.header.hash();
+	executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+
+	// `None` follows the new best block, while the pinned hash still reads the old state
+	assert_eq!(backend.best_hash(), block_hash);
+	assert_eq!(
+		StateBackend::storage(&backend, None, key.clone()).wait().unwrap(),
+		Some(StorageData(vec![42])),
+	);
+	assert_eq!(StateBackend::storage(&backend, Some(pinned), key).wait().unwrap(), None);
+}
+
+#[test]
+fn should_call_contract() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let genesis_hash = client.genesis_hash();
+	let (client, _child) = new_full(
+		client,
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	assert_matches!(
+		client.call("balanceOf".into(), Bytes(vec![1,2,3]), Some(genesis_hash).into(), None).wait(),
+		Err(Error::Client(_))
+	)
+}
+
+#[test]
+fn should_abort_runtime_calls_exceeding_timeout() {
+	use codec::Encode;
+
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let genesis_hash = client.genesis_hash();
+	let new_api = |timed_calls| new_full(
+		client.clone(),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		Some(timed_calls),
+	).0;
+	// far beyond the timeout, yet done within a fraction of a second
+	let expensive = Bytes(200_000u32.encode());
+
+	let timed_calls = TimedCalls::new(std::time::Duration::from_millis(1));
+	let api = new_api(timed_calls.clone());
+	for _ in 0..TIMED_OUT_CALLS_MAX {
+		assert_matches!(
+			api.call("TestAPI_hash_rounds".into(), expensive.clone(), Some(genesis_hash), None).wait(),
+			Err(Error::CallTimeout { timeout_ms: 1 })
+		);
+	}
+	// the timed out calls are still running, which also limits the APIs sharing `timed_calls`
+	let other = new_api(timed_calls);
+	assert_matches!(
+		other.call("TestAPI_benchmark_direct_call".into(), Bytes(Vec::new()), Some(genesis_hash), None).wait(),
+		Err(Error::TooManyRequests { max }) if max as usize == TIMED_OUT_CALLS_MAX
+	);
+
+	let api = new_api(TimedCalls::new(std::time::Duration::from_millis(1)));
+	assert_matches!(
+		api.call_trace("TestAPI_hash_rounds".into(), expensive, Some(genesis_hash)).wait(),
+		Err(Error::CallTimeout { timeout_ms: 1 })
+	);
+
+	// calls completing in time are not limited, however many run at the same time
+	let api = Arc::new(new_api(TimedCalls::new(std::time::Duration::from_secs(60))));
+	let calls = (0..TIMED_OUT_CALLS_MAX * 2)
+		.map(|_| {
+			let api = api.clone();
+			std::thread::spawn(move || {
+				api.call("TestAPI_hash_rounds".into(), Bytes(1_000u32.encode()), Some(genesis_hash), None).wait()
+			})
+		})
+		.collect::<Vec<_>>();
+	for call in calls {
+		assert_matches!(call.join().unwrap(), Ok(_));
+	}
+}
+
+#[test]
+fn should_notify_about_storage_changes() {
+	let (subscriber, id, transport) = Subscriber::new_test("test");
+
+	{
+		let mut client = Arc::new(substrate_test_runtime_client::new());
+		let (api, _child) = new_full(
+			client.clone(),
+			SubscriptionManager::new(Arc::new(TaskExecutor)),
+			DenyUnsafe::No,
+			None,
+		);
+
+		api.subscribe_storage(Default::default(), subscriber, None.into(), None, None, None, None);
+
+		// assert id assigned
+		assert!(matches!(
+			executor::block_on(id.compat()),
+			Ok(Ok(SubscriptionId::String(_)))
+		));
+
+		let mut builder = client.new_block(Default::default()).unwrap();
+		builder.push_transfer(runtime::Transfer {
+			from: AccountKeyring::Alice.into(),
+			to: AccountKeyring::Ferdie.into(),
+			amount: 42,
+			nonce: 0,
+		}).unwrap();
+		let block = builder.build().unwrap().block;
+		executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+	}
+
+	// assert notification sent to transport
+	let (notification, next) = executor::block_on(transport.into_future().compat()).unwrap();
+	assert!(notification.is_some());
+	// no more notifications on this channel
+	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
+}
+
+#[test]
+fn should_send_initial_storage_changes_and_notifications() {
+	let (subscriber, id, transport) = Subscriber::new_test("test");
+
+	{
+		let mut client = Arc::new(substrate_test_runtime_client::new());
+		let (api, _child) = new_full(
+			client.clone(),
+			SubscriptionManager::new(Arc::new(TaskExecutor)),
+			DenyUnsafe::No,
+			None,
+		);
+
+		let alice_balance_key = blake2_256(&runtime::system::balance_of_key(AccountKeyring::Alice.into()));
+
+		api.subscribe_storage(Default::default(), subscriber, Some(vec![
+			StorageKey(alice_balance_key.to_vec()),
+		]).into(), None, None, None, None);
+
+		// assert id assigned
+		assert!(matches!(
+			executor::block_on(id.compat()),
+			Ok(Ok(SubscriptionId::String(_)))
+		));
+
+		let mut builder = client.new_block(Default::default()).unwrap();
+		builder.push_transfer(runtime::Transfer {
+			from: AccountKeyring::Alice.into(),
+			to: AccountKeyring::Ferdie.into(),
+			amount: 42,
+			nonce: 0,
+		}).unwrap();
+		let block = builder.build().unwrap().block;
+		executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+	}
+
+	// assert initial values sent to transport
+	let (notification, next) = executor::block_on(transport.into_future().compat()).unwrap();
+	assert!(notification.is_some());
+	// assert notification sent to transport
+	let (notification, next) = executor::block_on(next.into_future().compat()).unwrap();
+	assert!(notification.is_some());
+	// no more notifications on this channel
+	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
+}
+
+#[test]
+fn should_only_send_storage_deletions() {
+	let (subscriber, id, transport) = Subscriber::new_test("test");
+
+	{
+		let mut client = Arc::new(TestClientBuilder::new()
+			.add_extra_storage(b":a".to_vec(), vec![1])
+			.add_extra_storage(b":b".to_vec(), vec![2])
+			.build());
+		let (api, _child) = new_full(
+			client.clone(),
+			SubscriptionManager::new(Arc::new(TaskExecutor)),
+			DenyUnsafe::No,
+			None,
+		);
+
+		api.subscribe_storage(Default::default(), subscriber, Some(vec![
+			StorageKey(b":a".to_vec()),
+			StorageKey(b":b".to_vec()),
+		]).into(), Some(true), None, None, None);
+
+		// assert id assigned
+		assert!(matches!(
+			executor::block_on(id.compat()),
+			Ok(Ok(SubscriptionId::String(_)))
+		));
+
+		// update of `:a` is suppressed
+		let mut builder = client.new_block(Default::default()).unwrap();
+		builder.push_storage_change(b":a".to_vec(), Some(vec![3])).unwrap();
+		let block = builder.build().unwrap().block;
+		executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+
+		// deletion of `:b` is delivered, update of `:a` is filtered out
+		let mut builder = client.new_block(Default::default()).unwrap();
+		builder.push_storage_change(b":a".to_vec(), Some(vec![4])).unwrap();
+		builder.push_storage_change(b":b".to_vec(), None).unwrap();
+		let block = builder.build().unwrap().block;
+		executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+	}
+
+	// assert only the deletion is sent to transport
+	let (notification, next) = executor::block_on(transport.into_future().compat()).unwrap();
+	let notification: serde_json::Value = serde_json::from_str(&notification.unwrap()).unwrap();
+	assert_eq!(
+		notification["params"]["result"]["changes"],
+		serde_json::json!([["0x3a62", null]]),
+	);
+	// no more notifications on this channel
+	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
+}
+
+#[test]
+fn should_coalesce_storage_changes() {
+	let (subscriber, id, transport) = Subscriber::new_test("test");
+
+	{
+		let mut client = Arc::new(substrate_test_runtime_client::new());
+		let (api, _child) = new_full(
+			client.clone(),
+			SubscriptionManager::new(Arc::new(TaskExecutor)),
+			DenyUnsafe::No,
+			None,
+		);
+
+		api.subscribe_storage(Default::default(), subscriber, Some(vec![
+			StorageKey(b":a".to_vec()),
+			StorageKey(b":b".to_vec()),
+		]).into(), None, Some(2), None, None);
+
+		// assert id assigned
+		assert!(matches!(
+			executor::block_on(id.compat()),
+			Ok(Ok(SubscriptionId::String(_)))
+		));
+
+		let changes = vec![
+			vec![(":a", Some(vec![1])), (":b", Some(vec![1]))],
+			vec![(":a", Some(vec![2]))],
+			vec![(":b", None)],
+			vec![(":b", Some(vec![3])), (":a", Some(vec![3]))],
+		];
+		for block_changes in changes {
+			let mut builder = client.new_block(Default::default()).unwrap();
+			for (key, value) in block_changes {
+				builder.push_storage_change(key.as_bytes().to_vec(), value).unwrap();
+			}
+			let block = builder.build().unwrap().block;
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		}
+	}
+
+	let changes_of = |notification: Option<String>| {
+		let notification: serde_json::Value = serde_json::from_str(&notification.unwrap()).unwrap();
+		notification["params"]["result"]["changes"].clone()
+	};
+
+	// initial values are sent as is
+	let (notification, next) = executor::block_on(transport.into_future().compat()).unwrap();
+	assert_eq!(changes_of(notification), serde_json::json!([["0x3a61", null], ["0x3a62", null]]));
+	// first two blocks are merged
+	let (notification, next) = executor::block_on(next.into_future().compat()).unwrap();
+	assert_eq!(changes_of(notification), serde_json::json!([["0x3a61", "0x02"], ["0x3a62", "0x01"]]));
+	// last two blocks are merged
+	let (notification, next) = executor::block_on(next.into_future().compat()).unwrap();
+	assert_eq!(changes_of(notification), serde_json::json!([["0x3a62", "0x03"], ["0x3a61", "0x03"]]));
+	// no more notifications on this channel
+	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
+}
+
+#[test]
+fn should_replay_storage_changes_from_block() {
+	let (subscriber, id, transport) = Subscriber::new_test("test");
+
+	{
+		let mut client = Arc::new(substrate_test_runtime_client::new());
+		let genesis_hash = client.genesis_hash();
+		let (api, _child) = new_full(
+			client.clone(),
+			SubscriptionManager::new(Arc::new(TaskExecutor)),
+			DenyUnsafe::No,
+			None,
+		);
+
+		let mut import_change = |value: u8| {
+			let mut builder = client.new_block(Default::default()).unwrap();
+			builder.push_storage_change(b":a".to_vec(), Some(vec![value])).unwrap();
+			let block = builder.build().unwrap().block;
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		};
+		import_change(1);
+		import_change(2);
+
+		api.subscribe_storage(Default::default(), subscriber, Some(vec![
+			StorageKey(b":a".to_vec()),
+		]).into(), None, None, None, Some(genesis_hash));
+
+		// assert id assigned
+		assert!(matches!(
+			executor::block_on(id.compat()),
+			Ok(Ok(SubscriptionId::String(_)))
+		));
+
+		import_change(3);
+	}
+
+	let changes_of = |notification: Option<String>| {
+		let notification: serde_json::Value = serde_json::from_str(&notification.unwrap()).unwrap();
+		notification["params"]["result"]["changes"].clone()
+	};
+
+	// missed changes are replayed first, starting with the values at `from_block`
+	let (notification, next) = executor::block_on(transport.into_future().compat()).unwrap();
+	assert_eq!(changes_of(notification), serde_json::json!([["0x3a61", null]]));
+	let (notification, next) = executor::block_on(next.into_future().compat()).unwrap();
+	assert_eq!(changes_of(notification), serde_json::json!([["0x3a61", "0x01"]]));
+	let (notification, next) = executor::block_on(next.into_future().compat()).unwrap();
+	assert_eq!(changes_of(notification), serde_json::json!([["0x3a61", "0x02"]]));
+	// followed by live changes
+	let (notification, next) = executor::block_on(next.into_future().compat()).unwrap();
+	assert_eq!(changes_of(notification), serde_json::json!([["0x3a61", "0x03"]]));
+	// no more notifications on this channel
+	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
+}
+
+#[test]
+fn should_not_replay_storage_changes_without_keys() {
+	let (subscriber, id, _transport) = Subscriber::new_test("test");
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let genesis_hash = client.genesis_hash();
+	let (api, _child) = new_full(
+		client,
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	api.subscribe_storage(Default::default(), subscriber, None, None, None, None, Some(genesis_hash));
+
+	// assert that the subscription was rejected
+	let err = executor::block_on(id.compat()).unwrap().unwrap_err();
+	assert_eq!(err.data.unwrap()["name"], "from_block");
+}
+
+#[test]
+fn should_only_send_finalized_storage_changes() {
+	let (subscriber, id, transport) = Subscriber::new_test("test");
+
+	{
+		let mut client = Arc::new(substrate_test_runtime_client::new());
+		let (api, _child) = new_full(
+			client.clone(),
+			SubscriptionManager::new(Arc::new(TaskExecutor)),
+			DenyUnsafe::No,
+			None,
+		);
+
+		api.subscribe_finalized_storage(Default::default(), subscriber, vec![StorageKey(b":a".to_vec())]);
+
+		// assert id assigned
+		assert!(matches!(
+			executor::block_on(id.compat()),
+			Ok(Ok(SubscriptionId::String(_)))
+		));
+
+		for value in 1..=2 {
+			let mut builder = client.new_block(Default::default()).unwrap();
+			builder.push_storage_change(b":a".to_vec(), Some(vec![value])).unwrap();
+			let block = builder.build().unwrap().block;
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		}
+		client.finalize_block(BlockId::number(1), None).unwrap();
+	}
+
+	let changes_of = |notification: Option<String>| {
+		let notification: serde_json::Value = serde_json::from_str(&notification.unwrap()).unwrap();
+		notification["params"]["result"]["changes"].clone()
+	};
+
+	// values at the last finalized block are sent first
+	let (notification, next) = executor::block_on(transport.into_future().compat()).unwrap();
+	assert_eq!(changes_of(notification), serde_json::json!([["0x3a61", null]]));
+	// followed by the changes of finalized blocks
+	let (notification, next) = executor::block_on(next.into_future().compat()).unwrap();
+	assert_eq!(changes_of(notification), serde_json::json!([["0x3a61", "0x01"]]));
+	// the change of the non-finalized block 2 is never sent
+	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
 }
 
 #[test]
-fn should_notify_about_storage_changes() {
+fn should_debounce_single_key_changes() {
 	let (subscriber, id, transport) = Subscriber::new_test("test");
+	let mut last_hash = Default::default();
 
 	{
 		let mut client = Arc::new(substrate_test_runtime_client::new());
@@ -162,9 +1463,11 @@ fn should_notify_about_storage_changes() {
 			client.clone(),
 			SubscriptionManager::new(Arc::new(TaskExecutor)),
 			DenyUnsafe::No,
+			None,
 		);
 
-		api.subscribe_storage(Default::default(), subscriber, None.into());
+		// long enough for all the changes below to fall into the first window
+		api.subscribe_storage_key(Default::default(), subscriber, StorageKey(b":a".to_vec()), 60_000);
 
 		// assert id assigned
 		assert!(matches!(
@@ -172,67 +1475,187 @@ fn should_notify_about_storage_changes() {
 			Ok(Ok(SubscriptionId::String(_)))
 		));
 
-		let mut builder = client.new_block(Default::default()).unwrap();
-		builder.push_transfer(runtime::Transfer {
-			from: AccountKeyring::Alice.into(),
-			to: AccountKeyring::Ferdie.into(),
-			amount: 42,
-			nonce: 0,
-		}).unwrap();
-		let block = builder.build().unwrap().block;
-		executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		for value in 1..=3 {
+			let mut builder = client.new_block(Default::default()).unwrap();
+			builder.push_storage_change(b":a".to_vec(), Some(vec![value])).unwrap();
+			let block = builder.build().unwrap().block;
+			last_hash = block.header.hash();
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		}
 	}
 
-	// assert notification sent to transport
+	let result_of = |notification: Option<String>| {
+		let notification: serde_json::Value = serde_json::from_str(&notification.unwrap()).unwrap();
+		notification["params"]["result"].clone()
+	};
+
+	// the value at the best block is sent first
 	let (notification, next) = executor::block_on(transport.into_future().compat()).unwrap();
-	assert!(notification.is_some());
-	// no more notifications on this channel
+	assert_eq!(result_of(notification)["changes"], serde_json::json!([["0x3a61", null]]));
+	// the window is still open when the change stream ends, so only the latest change is sent
+	let (notification, next) = executor::block_on(next.into_future().compat()).unwrap();
+	let result = result_of(notification);
+	assert_eq!(result["changes"], serde_json::json!([["0x3a61", "0x03"]]));
+	assert_eq!(result["block"], serde_json::to_value(last_hash).unwrap());
 	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
 }
 
 #[test]
-fn should_send_initial_storage_changes_and_notifications() {
+fn should_export_snapshot_in_batches() {
 	let (subscriber, id, transport) = Subscriber::new_test("test");
+	let child_info = ChildInfo::new_default(STORAGE_KEY);
+	let mut expected = HashMap::new();
 
 	{
-		let mut client = Arc::new(substrate_test_runtime_client::new());
+		// enough entries to span several batches
+		let mut builder = TestClientBuilder::new()
+			.add_extra_child_storage(&child_info, b":child".to_vec(), vec![42]);
+		for i in 0..1500u32 {
+			builder = builder.add_extra_storage(i.to_be_bytes().to_vec(), vec![1]);
+		}
+		let client = Arc::new(builder.build());
+		let genesis = BlockId::Hash(client.genesis_hash());
+		for (key, value) in client.storage_pairs(&genesis, &StorageKey(Vec::new())).unwrap() {
+			expected.insert((None, key), value);
+		}
+		expected.insert(
+			(Some(prefixed_storage_key()), StorageKey(b":child".to_vec())),
+			StorageData(vec![42]),
+		);
+
 		let (api, _child) = new_full(
-			client.clone(),
+			client,
 			SubscriptionManager::new(Arc::new(TaskExecutor)),
 			DenyUnsafe::No,
+			None,
 		);
 
-		let alice_balance_key = blake2_256(&runtime::system::balance_of_key(AccountKeyring::Alice.into()));
-
-		api.subscribe_storage(Default::default(), subscriber, Some(vec![
-			StorageKey(alice_balance_key.to_vec()),
-		]).into());
+		api.export_snapshot(Default::default(), subscriber, None);
 
 		// assert id assigned
 		assert!(matches!(
 			executor::block_on(id.compat()),
 			Ok(Ok(SubscriptionId::String(_)))
 		));
+	}
 
-		let mut builder = client.new_block(Default::default()).unwrap();
-		builder.push_transfer(runtime::Transfer {
-			from: AccountKeyring::Alice.into(),
-			to: AccountKeyring::Ferdie.into(),
-			amount: 42,
-			nonce: 0,
-		}).unwrap();
-		let block = builder.build().unwrap().block;
-		executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+	let mut exported = HashMap::new();
+	let mut batches = 0;
+	let mut next = transport;
+	loop {
+		let (notification, rest) = executor::block_on(next.into_future().compat()).unwrap();
+		let notification = match notification {
+			Some(notification) => notification,
+			None => break,
+		};
+		let notification: serde_json::Value = serde_json::from_str(&notification).unwrap();
+		let batch: SnapshotBatch = serde_json::from_value(notification["params"]["result"].clone()).unwrap();
+		assert!(batch.pairs.len() <= SNAPSHOT_BATCH_SIZE);
+		for (key, value) in batch.pairs {
+			exported.insert((batch.child.clone(), key), value);
+		}
+		batches += 1;
+		next = rest;
 	}
 
-	// assert initial values sent to transport
+	assert!(batches > 2);
+	assert_eq!(exported, expected);
+}
+
+#[test]
+fn should_export_one_snapshot_at_a_time() {
+	// enough batches to keep the first export running until its notifications are read
+	let mut builder = TestClientBuilder::new();
+	for i in 0..5000u32 {
+		builder = builder.add_extra_storage(i.to_be_bytes().to_vec(), vec![1]);
+	}
+	let (api, _child) = new_full(
+		Arc::new(builder.build()),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	let (subscriber, id, transport) = Subscriber::new_test("test");
+	api.export_snapshot(Default::default(), subscriber, None);
+	assert_matches!(executor::block_on(id.compat()), Ok(Ok(SubscriptionId::String(_))));
+
+	// rejected even though no heavy request limit is set
+	let (subscriber, id, _transport) = Subscriber::new_test("test");
+	api.export_snapshot(Default::default(), subscriber, None);
+	let err = executor::block_on(id.compat()).unwrap().unwrap_err();
+	let expected: rpc::Error = Error::TooManyRequests { max: SNAPSHOTS_MAX as u32 }.into();
+	assert_eq!(err, expected);
+
+	// the slot is released once the first export completes
+	executor::block_on(transport.collect().compat()).unwrap();
+	let (subscriber, id, _transport) = Subscriber::new_test("test");
+	api.export_snapshot(Default::default(), subscriber, None);
+	assert_matches!(executor::block_on(id.compat()), Ok(Ok(SubscriptionId::String(_))));
+}
+
+#[test]
+fn should_not_export_snapshot_when_unsafe_denied() {
+	let (subscriber, id, _transport) = Subscriber::new_test("test");
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let (api, _child) = new_full(
+		client,
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::Yes,
+		None,
+	);
+
+	api.export_snapshot(Default::default(), subscriber, None);
+
+	// assert that the subscription was rejected
+	assert!(matches!(executor::block_on(id.compat()), Ok(Err(_))));
+}
+
+#[test]
+fn should_send_heartbeats_when_idle() {
+	let (subscriber, id, transport) = Subscriber::new_test("test");
+
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let genesis_hash = client.genesis_hash();
+	let (api, _child) = new_full(
+		client,
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+
+	api.subscribe_storage(Default::default(), subscriber, Some(vec![
+		StorageKey(b":a".to_vec()),
+	]).into(), None, None, Some(10), None);
+
+	// assert id assigned
+	assert!(matches!(
+		executor::block_on(id.compat()),
+		Ok(Ok(SubscriptionId::String(_)))
+	));
+
+	let result_of = |notification: Option<String>| {
+		let notification: serde_json::Value = serde_json::from_str(&notification.unwrap()).unwrap();
+		notification["params"]["result"].clone()
+	};
+
+	// initial values
 	let (notification, next) = executor::block_on(transport.into_future().compat()).unwrap();
-	assert!(notification.is_some());
-	// assert notification sent to transport
-	let (notification, next) = executor::block_on(next.into_future().compat()).unwrap();
-	assert!(notification.is_some());
-	// no more notifications on this channel
-	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
+	assert_eq!(result_of(notification)["changes"], serde_json::json!([["0x3a61", null]]));
+	// no block is imported, so only heartbeats follow
+	let mut next = next;
+	for _ in 0..2 {
+		let (notification, rest) = executor::block_on(next.into_future().compat()).unwrap();
+		let result = result_of(notification);
+		assert_eq!(result["changes"], serde_json::json!([]));
+		assert_eq!(result["block"], serde_json::to_value(genesis_hash).unwrap());
+		next = rest;
+	}
+
+	let (subscriber, id, _transport) = Subscriber::new_test("test");
+	api.subscribe_storage(Default::default(), subscriber, None, None, None, Some(0), None);
+	let err = executor::block_on(id.compat()).unwrap().unwrap_err();
+	assert_eq!(err.data.unwrap()["name"], "heartbeat_ms");
 }
 
 #[test]
@@ -242,6 +1665,7 @@ fn should_query_storage() {
 			client.clone(),
 			SubscriptionManager::new(Arc::new(TaskExecutor)),
 			DenyUnsafe::No,
+			None,
 		);
 
 		let mut add_block = |nonce| {
@@ -446,6 +1870,98 @@ fn should_query_storage() {
 	);
 }
 
+#[test]
+fn should_return_changed_keys() {
+	fn run_tests(mut client: Arc<TestClient>, has_changes_trie_config: bool) {
+		let (api, _child) = new_full(
+			client.clone(),
+			SubscriptionManager::new(Arc::new(TaskExecutor)),
+			DenyUnsafe::No,
+			None,
+		);
+		let genesis_hash = client.genesis_hash();
+
+		let mut add_block = |changes: Vec<(&str, Option<Vec<u8>>)>| {
+			let mut builder = client.new_block(Default::default()).unwrap();
+			for (key, value) in changes {
+				builder.push_storage_change(key.as_bytes().to_vec(), value).unwrap();
+			}
+			let block = builder.build().unwrap().block;
+			let hash = block.header.hash();
+			executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+			hash
+		};
+		let block1_hash = add_block(vec![(":t:1", Some(vec![1])), (":t:2", Some(vec![2]))]);
+		let block2_hash = add_block(vec![(":t:2", None), (":t:3", Some(vec![3])), (":u:1", Some(vec![1]))]);
+		// rewrites a value it already had
+		let block3_hash = add_block(vec![(":t:3", Some(vec![3])), (":t:4", Some(vec![4]))]);
+		let prefix = Some(StorageKey(b":t:".to_vec()));
+		let keys = |keys: &[&str]| keys.iter().map(|k| StorageKey(k.as_bytes().to_vec())).collect::<Vec<_>>();
+
+		assert_eq!(
+			api.changed_keys(genesis_hash, Some(block2_hash), prefix.clone()).wait().unwrap(),
+			keys(&[":t:1", ":t:2", ":t:3"]),
+		);
+		assert_eq!(
+			api.changed_keys(block1_hash, Some(block2_hash), prefix.clone()).wait().unwrap(),
+			keys(&[":t:2", ":t:3"]),
+		);
+		assert_eq!(
+			api.changed_keys(block2_hash, Some(block3_hash), prefix.clone()).wait().unwrap(),
+			keys(&[":t:4"]),
+		);
+		assert_eq!(
+			api.changed_keys(block3_hash, None, prefix.clone()).wait().unwrap(),
+			Vec::<StorageKey>::new(),
+		);
+		// without a prefix, keys written by the runtime itself are returned as well
+		let all = api.changed_keys(block1_hash, Some(block2_hash), None).wait().unwrap();
+		assert!(keys(&[":t:2", ":t:3", ":u:1"]).iter().all(|key| all.contains(key)));
+		assert!(!all.contains(&StorageKey(b":t:1".to_vec())));
+		assert_matches!(
+			api.changed_keys(block2_hash, Some(genesis_hash), prefix).wait(),
+			Err(Error::InvalidBlockRange { .. })
+		);
+
+		// blocks without changes tries are executed again, so fewer of them may be queried
+		let mut last_hash = block3_hash;
+		for _ in 0..CHANGED_KEYS_MAX_EXECUTED_BLOCKS {
+			last_hash = add_block(vec![]);
+		}
+		let changed = api.changed_keys(genesis_hash, Some(last_hash), prefix.clone()).wait();
+		if has_changes_trie_config {
+			assert_eq!(changed.unwrap(), keys(&[":t:1", ":t:2", ":t:3", ":t:4"]));
+		} else {
+			assert_matches!(changed, Err(Error::InvalidBlockRange { .. }));
+		}
+		assert_eq!(
+			api.changed_keys(block3_hash, Some(last_hash), prefix).wait().unwrap(),
+			Vec::<StorageKey>::new(),
+		);
+
+		let (api, _child) = new_full(
+			client.clone(),
+			SubscriptionManager::new(Arc::new(TaskExecutor)),
+			DenyUnsafe::Yes,
+			None,
+		);
+		assert_matches!(
+			api.changed_keys(genesis_hash, None, None).wait(),
+			Err(Error::UnsafeRpcCalled(_))
+		);
+	}
+
+	run_tests(Arc::new(substrate_test_runtime_client::new()), false);
+	run_tests(
+		Arc::new(
+			TestClientBuilder::new()
+				.changes_trie_config(Some(ChangesTrieConfiguration::new(4, 2)))
+				.build(),
+		),
+		true,
+	);
+}
+
 #[test]
 fn should_split_ranges() {
 	assert_eq!(split_range(1, None), (0..1, None));
@@ -463,6 +1979,7 @@ fn should_return_runtime_version() {
 		client.clone(),
 		SubscriptionManager::new(Arc::new(TaskExecutor)),
 		DenyUnsafe::No,
+		None,
 	);
 
 	let result = "{\"specName\":\"test\",\"implName\":\"parity-test\",\"authoringVersion\":1,\
@@ -480,6 +1997,32 @@ fn should_return_runtime_version() {
 	assert_eq!(deserialized, runtime_version);
 }
 
+#[test]
+fn should_return_runtime_version_at_number_on_light_node() {
+	use codec::Encode;
+
+	let full_client = substrate_test_runtime_client::new();
+	let full_version = full_client.runtime_version_at(&BlockId::number(0)).unwrap();
+
+	let (client, backend) = substrate_test_runtime_client::new_light();
+	let encoded_version = full_version.encode();
+	let fetcher = substrate_test_runtime_client::new_light_fetcher()
+		.with_remote_call(Some(Box::new(move |_| Ok(encoded_version.clone()))));
+	let (api, _child) = new_light(
+		Arc::new(client),
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		backend.blockchain().clone(),
+		Arc::new(fetcher),
+		DenyUnsafe::No,
+	);
+
+	assert_eq!(api.runtime_version_at_number(0u64.into()).wait().unwrap(), full_version);
+	assert_matches!(
+		api.runtime_version_at_number(5u64.into()).wait(),
+		Err(Error::InvalidParameter { name, .. }) if name == "number"
+	);
+}
+
 #[test]
 fn should_notify_on_runtime_version_initially() {
 	let (subscriber, id, transport) = Subscriber::new_test("test");
@@ -490,6 +2033,7 @@ fn should_notify_on_runtime_version_initially() {
 			client.clone(),
 			SubscriptionManager::new(Arc::new(TaskExecutor)),
 			DenyUnsafe::No,
+			None,
 		);
 
 		api.subscribe_runtime_version(Default::default(), subscriber);
@@ -509,6 +2053,274 @@ fn should_notify_on_runtime_version_initially() {
 	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
 }
 
+#[test]
+fn should_use_custom_backend() {
+	fn unsupported_err() -> Error {
+		client_err(sp_blockchain::Error::Backend("not served by the mock backend".into()))
+	}
+
+	fn unsupported<T: Send + 'static>() -> FutureResult<T> {
+		Box::new(result(Err(unsupported_err())))
+	}
+
+	/// Backend serving `storage` by echoing the key.
+	struct MockBackend;
+
+	impl StateBackend<runtime::Block, ()> for MockBackend {
+		fn best_hash(&self) -> H256 {
+			Default::default()
+		}
+		fn finalized_hash(&self) -> H256 {
+			Default::default()
+		}
+		fn genesis_hash(&self) -> H256 {
+			Default::default()
+		}
+		fn is_finalized(&self, _: H256) -> FutureResult<bool> {
+			unsupported()
+		}
+		fn block_hashes(&self, _: Vec<u64>) -> FutureResult<Vec<Option<H256>>> {
+			unsupported()
+		}
+		fn call(&self, _: Option<H256>, _: String, _: Bytes) -> FutureResult<Bytes> {
+			unsupported()
+		}
+		fn call_trace(&self, _: Option<H256>, _: String, _: Bytes) -> FutureResult<CallTrace> {
+			unsupported()
+		}
+		fn storage_keys(&self, _: Option<H256>, _: StorageKey) -> FutureResult<Vec<StorageKey>> {
+			unsupported()
+		}
+		fn storage_pairs(&self, _: Option<H256>, _: StorageKey) -> FutureResult<Vec<(StorageKey, StorageData)>> {
+			unsupported()
+		}
+		fn child_keys(&self, _: Option<H256>) -> FutureResult<Vec<PrefixedStorageKey>> {
+			unsupported()
+		}
+		fn storage_keys_paged(
+			&self,
+			_: Option<H256>,
+			_: Option<StorageKey>,
+			_: u32,
+			_: Option<StorageKey>,
+		) -> FutureResult<Vec<StorageKey>> {
+			unsupported()
+		}
+		fn storage(&self, _: Option<H256>, key: StorageKey) -> FutureResult<Option<StorageData>> {
+			Box::new(result(Ok(Some(StorageData(key.0)))))
+		}
+		fn storage_values(&self, _: Option<H256>, keys: Vec<StorageKey>) -> FutureResult<Vec<Option<StorageData>>> {
+			Box::new(result(Ok(keys.into_iter().map(|key| Some(StorageData(key.0))).collect())))
+		}
+		fn storage_hash(&self, _: Option<H256>, _: StorageKey) -> FutureResult<Option<H256>> {
+			unsupported()
+		}
+		fn storage_size(&self, _: Option<H256>, _: StorageKey) -> FutureResult<Option<u64>> {
+			unsupported()
+		}
+		fn prefix_size(&self, _: Option<H256>, _: StorageKey) -> FutureResult<PrefixSize> {
+			unsupported()
+		}
+		fn diff_prefixes(&self, _: Option<H256>, _: StorageKey, _: StorageKey) -> FutureResult<PrefixDiff> {
+			unsupported()
+		}
+		fn exists(&self, _: Option<H256>, _: StorageKey) -> FutureResult<bool> {
+			unsupported()
+		}
+		fn state_root(&self, _: Option<H256>) -> FutureResult<H256> {
+			unsupported()
+		}
+		fn metadata(&self, _: Option<H256>) -> FutureResult<Bytes> {
+			unsupported()
+		}
+		fn runtime_version(&self, _: Option<H256>) -> FutureResult<RuntimeVersion> {
+			unsupported()
+		}
+		fn runtime_version_at_number(&self, _: u64) -> FutureResult<RuntimeVersion> {
+			unsupported()
+		}
+		fn query_storage(
+			&self,
+			_: H256,
+			_: Option<H256>,
+			_: Vec<StorageKey>,
+		) -> FutureResult<Vec<StorageChangeSet<H256>>> {
+			unsupported()
+		}
+		fn query_storage_at(&self, _: Vec<StorageKey>, _: Option<H256>) -> FutureResult<Vec<StorageChangeSet<H256>>> {
+			unsupported()
+		}
+		fn changed_keys(&self, _: H256, _: Option<H256>, _: Option<StorageKey>) -> FutureResult<Vec<StorageKey>> {
+			unsupported()
+		}
+		fn read_proof(&self, _: Option<H256>, _: Vec<StorageKey>) -> FutureResult<ReadProof<H256>> {
+			unsupported()
+		}
+		fn combined_read_proof(
+			&self,
+			_: Option<H256>,
+			_: Vec<StorageKey>,
+			_: Vec<(PrefixedStorageKey, Vec<StorageKey>)>,
+		) -> FutureResult<ReadProof<H256>> {
+			unsupported()
+		}
+		fn subscribe_runtime_version(&self, _: crate::Metadata, subscriber: Subscriber<RuntimeVersion>) {
+			let _ = subscriber.reject(unsupported_err().into());
+		}
+		fn unsubscribe_runtime_version(&self, _: Option<crate::Metadata>, _: SubscriptionId) -> RpcResult<bool> {
+			Ok(false)
+		}
+		fn subscribe_storage(
+			&self,
+			_: crate::Metadata,
+			subscriber: Subscriber<StorageChangeSet<H256>>,
+			_: Option<Vec<StorageKey>>,
+			_: bool,
+			_: u32,
+			_: Option<std::time::Duration>,
+			_: Option<H256>,
+		) {
+			let _ = subscriber.reject(unsupported_err().into());
+		}
+		fn unsubscribe_storage(&self, _: Option<crate::Metadata>, _: SubscriptionId) -> RpcResult<bool> {
+			Ok(false)
+		}
+		fn subscribe_finalized_storage(
+			&self,
+			_: crate::Metadata,
+			subscriber: Subscriber<StorageChangeSet<H256>>,
+			_: Vec<StorageKey>,
+		) {
+			let _ = subscriber.reject(unsupported_err().into());
+		}
+		fn unsubscribe_finalized_storage(&self, _: Option<crate::Metadata>, _: SubscriptionId) -> RpcResult<bool> {
+			Ok(false)
+		}
+		fn subscribe_storage_key(
+			&self,
+			_: crate::Metadata,
+			subscriber: Subscriber<StorageChangeSet<H256>>,
+			_: StorageKey,
+			_: Option<std::time::Duration>,
+		) {
+			let _ = subscriber.reject(unsupported_err().into());
+		}
+		fn unsubscribe_storage_key(&self, _: Option<crate::Metadata>, _: SubscriptionId) -> RpcResult<bool> {
+			Ok(false)
+		}
+		fn export_snapshot(
+			&self,
+			_: crate::Metadata,
+			subscriber: Subscriber<SnapshotBatch>,
+			_: Option<H256>,
+			_: Box<dyn Send>,
+		) {
+			let _ = subscriber.reject(unsupported_err().into());
+		}
+		fn unsubscribe_snapshot(&self, _: Option<crate::Metadata>, _: SubscriptionId) -> RpcResult<bool> {
+			Ok(false)
+		}
+		fn trace_block(
+			&self,
+			_: H256,
+			_: Option<String>,
+			_: Option<String>,
+		) -> FutureResult<sp_rpc::tracing::TraceBlockResponse> {
+			unsupported()
+		}
+	}
+
+	let api = State::<runtime::Block, ()>::from_backend(Box::new(MockBackend), DenyUnsafe::Yes);
+	assert_eq!(
+		api.storage(StorageKey(vec![42]), None).wait().unwrap(),
+		Some(StorageData(vec![42])),
+	);
+	assert_matches!(
+		api.storage_pairs(StorageKey(vec![]), None, None).wait(),
+		Err(Error::UnsafeRpcCalled(_))
+	);
+}
+
+#[test]
+fn should_reject_heavy_requests_above_limit() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let (api, _child) = new_full(
+		client,
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+	let api = api.with_heavy_request_limit(2);
+
+	// requests hold their slot until they are resolved or dropped
+	let first = api.storage_pairs(StorageKey(vec![]), None, None);
+	let _second = api.storage_pairs(StorageKey(vec![]), None, None);
+	assert_matches!(
+		api.storage_pairs(StorageKey(vec![]), None, None).wait(),
+		Err(Error::TooManyRequests { max: 2 })
+	);
+
+	// light requests are not limited
+	assert_matches!(
+		api.storage(StorageKey(well_known_keys::CODE.to_vec()), None).wait(),
+		Ok(Some(_))
+	);
+
+	// dropping a heavy request frees its slot
+	drop(first);
+	assert_matches!(api.storage_pairs(StorageKey(vec![]), None, None).wait(), Ok(_));
+}
+
+struct Syncing;
+
+impl SyncStatusProvider for Syncing {
+	fn major_sync_status(&self) -> Option<(u64, u64)> {
+		Some((10, 1000))
+	}
+}
+
+#[test]
+fn should_reject_heavy_requests_while_syncing() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let genesis_hash = client.genesis_hash();
+	let (api, _child) = new_full(
+		client,
+		SubscriptionManager::new(Arc::new(TaskExecutor)),
+		DenyUnsafe::No,
+		None,
+	);
+	let api = api.with_sync_status(Arc::new(Syncing));
+
+	assert_matches!(
+		api.storage_pairs(StorageKey(vec![]), None, None).wait(),
+		Err(Error::NodeSyncing { best: 10, target: 1000 })
+	);
+	let err: rpc::Error = api.storage_pairs(StorageKey(vec![]), None, None).wait().unwrap_err().into();
+	assert_eq!(err.data, Some(serde_json::json!({ "best": 10, "target": 1000, "retry": true })));
+	assert_matches!(
+		api.call_trace("Core_version".into(), Bytes(Vec::new()), None).wait(),
+		Err(Error::NodeSyncing { .. })
+	);
+	assert_matches!(
+		api.read_proof_paged(vec![StorageKey(well_known_keys::CODE.to_vec())], 1, 0, None).wait(),
+		Err(Error::NodeSyncing { .. })
+	);
+	assert_matches!(
+		api.combined_read_proof(vec![StorageKey(well_known_keys::CODE.to_vec())], Vec::new(), None).wait(),
+		Err(Error::NodeSyncing { .. })
+	);
+	assert_matches!(
+		api.storage_matrix(vec![StorageKey(well_known_keys::CODE.to_vec())], vec![genesis_hash]).wait(),
+		Err(Error::NodeSyncing { .. })
+	);
+
+	// other requests are still served
+	assert_matches!(
+		api.storage(StorageKey(well_known_keys::CODE.to_vec()), None).wait(),
+		Ok(Some(_))
+	);
+}
+
 #[test]
 fn should_deserialize_storage_key() {
 	let k = "\"0x7f864e18e3dd8b58386310d2fe0919eef27c6e558564b7f67f22d99d20f587b\"";
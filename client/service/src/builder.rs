@@ -46,7 +46,7 @@ use sc_network::block_request_handler::{self, BlockRequestHandler};
 use sc_network::light_client_requests::{self, handler::LightClientRequestHandler};
 use sp_runtime::generic::BlockId;
 use sp_runtime::traits::{
-	Block as BlockT, HashFor, Zero, BlockIdTo,
+	Block as BlockT, HashFor, Zero, BlockIdTo, SaturatedConversion,
 };
 use sp_api::{ProvideRuntimeApi, CallApiAt};
 use sc_executor::{NativeExecutor, NativeExecutionDispatch, RuntimeInfo};
@@ -656,6 +656,14 @@ pub fn spawn_tasks<TBl, TBackend, TExPool, TRpc, TCl>(
 	);
 
 	// RPC
+	let sync_status = if config.rpc_reject_while_syncing {
+		Some(Arc::new(NetworkSyncStatus { client: client.clone(), network: network.clone() })
+			as Arc<dyn sc_rpc::state::SyncStatusProvider>)
+	} else {
+		None
+	};
+	// shared by all transports, so that timed out calls are limited node-wide
+	let timed_calls = config.rpc_call_timeout.map(sc_rpc::state::TimedCalls::new);
 	let gen_handler = |
 		deny_unsafe: sc_rpc::DenyUnsafe,
 		rpc_middleware: sc_rpc_server::RpcMiddleware
@@ -663,7 +671,7 @@ pub fn spawn_tasks<TBl, TBackend, TExPool, TRpc, TCl>(
 		deny_unsafe, rpc_middleware, &config, task_manager.spawn_handle(),
 		client.clone(), transaction_pool.clone(), keystore.clone(),
 		on_demand.clone(), remote_blockchain.clone(), &*rpc_extensions_builder,
-		backend.offchain_storage(), system_rpc_tx.clone()
+		backend.offchain_storage(), system_rpc_tx.clone(), sync_status.clone(), timed_calls.clone()
 	);
 	let rpc_metrics = sc_rpc_server::RpcMetrics::new(config.prometheus_registry())?;
 	let rpc = start_rpc_servers(&config, gen_handler, rpc_metrics.clone())?;
@@ -739,6 +747,27 @@ fn init_telemetry<TBl: BlockT, TCl: BlockBackend<TBl>>(
 	Ok(telemetry.handle())
 }
 
+/// Reports the major sync status of the node to the state RPC.
+struct NetworkSyncStatus<TBl: BlockT, TCl> {
+	client: Arc<TCl>,
+	network: Arc<NetworkService<TBl, <TBl as BlockT>::Hash>>,
+}
+
+impl<TBl, TCl> sc_rpc::state::SyncStatusProvider for NetworkSyncStatus<TBl, TCl>
+	where
+		TBl: BlockT,
+		TCl: HeaderBackend<TBl> + Send + Sync,
+{
+	fn major_sync_status(&self) -> Option<(u64, u64)> {
+		if !self.network.is_major_syncing() {
+			return None
+		}
+		let best = self.client.info().best_number;
+		let target = self.network.best_seen_block().unwrap_or(best);
+		Some((best.saturated_into(), target.saturated_into()))
+	}
+}
+
 fn gen_handler<TBl, TBackend, TExPool, TRpc, TCl>(
 	deny_unsafe: sc_rpc::DenyUnsafe,
 	rpc_middleware: sc_rpc_server::RpcMiddleware,
@@ -751,7 +780,9 @@ fn gen_handler<TBl, TBackend, TExPool, TRpc, TCl>(
 	remote_blockchain: Option<Arc<dyn RemoteBlockchain<TBl>>>,
 	rpc_extensions_builder: &(dyn RpcExtensionBuilder<Output = TRpc> + Send),
 	offchain_storage: Option<<TBackend as sc_client_api::backend::Backend<TBl>>::OffchainStorage>,
-	system_rpc_tx: TracingUnboundedSender<sc_rpc::system::Request<TBl>>
+	system_rpc_tx: TracingUnboundedSender<sc_rpc::system::Request<TBl>>,
+	sync_status: Option<Arc<dyn sc_rpc::state::SyncStatusProvider>>,
+	timed_calls: Option<sc_rpc::state::TimedCalls>,
 ) -> sc_rpc_server::RpcHandler<sc_rpc::Metadata>
 	where
 		TBl: BlockT,
@@ -804,9 +835,20 @@ fn gen_handler<TBl, TBackend, TExPool, TRpc, TCl>(
 			client.clone(),
 			subscriptions.clone(),
 			deny_unsafe,
+			timed_calls,
 		);
 		(chain, state, child_state)
 	};
+	let state = match config.rpc_max_heavy_requests {
+		Some(max) => state.with_heavy_request_limit(max),
+		None => state,
+	};
+	let state = state.with_forbidden_methods(config.rpc_forbidden_calls.iter().cloned())
+		.with_compression(!config.rpc_disable_compression);
+	let state = match sync_status {
+		Some(sync_status) => state.with_sync_status(sync_status),
+		None => state,
+	};
 
 	let author = sc_rpc::author::Author::new(
 		client,
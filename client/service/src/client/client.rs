@@ -51,7 +51,7 @@ use sp_runtime::{
 use sp_state_machine::{
 	DBValue, Backend as StateBackend, ChangesTrieAnchorBlockId,
 	prove_read, prove_child_read, ChangesTrieRootsStorage, ChangesTrieStorage,
-	ChangesTrieConfigurationRange, key_changes, key_changes_proof,
+	ChangesTrieConfigurationRange, key_changes, key_changes_proof, changes_trie_changed_keys,
 };
 use sc_executor::RuntimeVersion;
 use sp_consensus::{
@@ -1407,6 +1407,21 @@ impl<B, E, Block, RA> StorageProvider<Block, B> for Client<B, E, Block, RA> wher
 		Ok(KeyIterator::new(state, prefix, start_key))
 	}
 
+	fn child_storage_keys_iter<'a>(
+		&self,
+		id: &BlockId<Block>,
+		child_info: ChildInfo,
+		prefix: Option<&'a StorageKey>,
+		start_key: Option<&StorageKey>
+	) -> sp_blockchain::Result<KeyIterator<'a, B::State, Block>> {
+		let state = self.state_at(id)?;
+		let start_key = start_key
+			.or(prefix)
+			.map(|key| key.0.clone())
+			.unwrap_or_else(Vec::new);
+		Ok(KeyIterator::new_child(state, child_info, prefix, start_key))
+	}
+
 
 	fn storage(
 		&self,
@@ -1419,6 +1434,19 @@ impl<B, E, Block, RA> StorageProvider<Block, B> for Client<B, E, Block, RA> wher
 		)
 	}
 
+	fn storage_values(
+		&self,
+		id: &BlockId<Block>,
+		keys: &[StorageKey],
+	) -> sp_blockchain::Result<Vec<Option<StorageData>>> {
+		let state = self.state_at(id)?;
+		keys.iter()
+			.map(|key| state.storage(&key.0)
+				.map(|value| value.map(StorageData))
+				.map_err(|e| sp_blockchain::Error::from_state(Box::new(e)))
+			)
+			.collect()
+	}
 
 	fn storage_hash(
 		&self,
@@ -1539,6 +1567,26 @@ impl<B, E, Block, RA> StorageProvider<Block, B> for Client<B, E, Block, RA> wher
 
 		Ok(result)
 	}
+
+	fn changed_keys(
+		&self,
+		id: &BlockId<Block>,
+		prefix: Option<&StorageKey>,
+	) -> sp_blockchain::Result<Vec<StorageKey>> {
+		let number = self.backend.blockchain().expect_block_number_from_id(id)?;
+		let hash = self.backend.blockchain().expect_block_hash_from_id(id)?;
+		let storage = self.backend.changes_trie_storage()
+			.ok_or_else(|| sp_blockchain::Error::ChangesTriesNotSupported)?;
+		if storage.configuration_at(&BlockId::Hash(hash))?.config.is_none() {
+			return Err(sp_blockchain::Error::ChangesTriesNotSupported);
+		}
+
+		let anchor = ChangesTrieAnchorBlockId { hash: convert_hash(&hash), number };
+		let prefix = prefix.map(|prefix| &prefix.0[..]).unwrap_or(&[]);
+		changes_trie_changed_keys::<HashFor<Block>, _>(storage.storage(), &anchor, prefix)
+			.map(|keys| keys.into_iter().map(StorageKey).collect())
+			.map_err(|err| sp_blockchain::Error::ChangesTrieAccessFailed(err))
+	}
 }
 
 impl<B, E, Block, RA> HeaderMetadata<Block> for Client<B, E, Block, RA> where
@@ -31,7 +31,9 @@ pub use sc_network::config::{
 pub use sc_executor::WasmExecutionMethod;
 pub use sc_client_api::execution_extensions::{ExecutionStrategies, ExecutionStrategy};
 
-use std::{io, future::Future, path::{PathBuf, Path}, pin::Pin, net::SocketAddr, sync::Arc};
+use std::{
+	io, future::Future, path::{PathBuf, Path}, pin::Pin, net::SocketAddr, sync::Arc, time::Duration,
+};
 pub use sc_transaction_pool::txpool::Options as TransactionPoolOptions;
 use sc_chain_spec::ChainSpec;
 use sp_core::crypto::SecretString;
@@ -93,6 +95,17 @@ pub struct Configuration {
 	pub rpc_cors: Option<Vec<String>>,
 	/// RPC methods to expose (by default only a safe subset or all of them).
 	pub rpc_methods: RpcMethods,
+	/// Maximum execution time of runtime calls made through `state_call` and `state_callTrace`.
+	/// `None` if unbounded.
+	pub rpc_call_timeout: Option<Duration>,
+	/// Maximum number of heavy state RPC requests processed concurrently. `None` if unlimited.
+	pub rpc_max_heavy_requests: Option<usize>,
+	/// Runtime methods that may not be called through `state_call`.
+	pub rpc_forbidden_calls: Vec<String>,
+	/// Refuse to return compressed results from `state_getKeys` and `state_getPairs`.
+	pub rpc_disable_compression: bool,
+	/// Reject unsafe and heavy state queries while the node is major syncing.
+	pub rpc_reject_while_syncing: bool,
 	/// Prometheus endpoint configuration. `None` if disabled.
 	pub prometheus_config: Option<PrometheusConfig>,
 	/// Telemetry service URL. `None` if disabled.
@@ -50,7 +50,7 @@ use sp_consensus::{
 	BlockOrigin, SelectChain, BlockImport, Error as ConsensusError, BlockCheckParams, ImportResult,
 	BlockStatus, BlockImportParams, ForkChoiceStrategy,
 };
-use sp_storage::StorageKey;
+use sp_storage::{ChildInfo, StorageKey};
 use sp_trie::{TrieConfiguration, trie_types::Layout};
 use sp_runtime::{generic::BlockId, DigestItem, Justifications};
 use hex_literal::hex;
@@ -1764,6 +1764,35 @@ fn storage_keys_iter_prefix_and_start_key_works() {
 	assert_eq!(res, Vec::<Vec<u8>>::new());
 }
 
+#[test]
+fn child_storage_keys_iter_works() {
+	let child_info = ChildInfo::new_default(b"child");
+	let client = TestClientBuilder::new()
+		.add_extra_child_storage(&child_info, b"a".to_vec(), vec![1])
+		.add_extra_child_storage(&child_info, b"b1".to_vec(), vec![2])
+		.add_extra_child_storage(&child_info, b"b2".to_vec(), vec![3])
+		.build();
+
+	let res: Vec<_> = client.child_storage_keys_iter(&BlockId::Number(0), child_info.clone(), None, None)
+		.unwrap()
+		.map(|x| x.0)
+		.collect();
+	assert_eq!(res, [b"a".to_vec(), b"b1".to_vec(), b"b2".to_vec()]);
+
+	let prefix = StorageKey(b"b".to_vec());
+	let res: Vec<_> = client.child_storage_keys_iter(&BlockId::Number(0), child_info.clone(), Some(&prefix), None)
+		.unwrap()
+		.map(|x| x.0)
+		.collect();
+	assert_eq!(res, [b"b1".to_vec(), b"b2".to_vec()]);
+
+	let res: Vec<_> = client.child_storage_keys_iter(&BlockId::Number(0), child_info, Some(&prefix), Some(&StorageKey(b"b1".to_vec())))
+		.unwrap()
+		.map(|x| x.0)
+		.collect();
+	assert_eq!(res, [b"b2".to_vec()]);
+}
+
 #[test]
 fn storage_keys_iter_works() {
 	let client = substrate_test_runtime_client::new();
@@ -25,8 +25,10 @@ use hash_db::Hasher;
 use num_traits::Zero;
 use sp_core::storage::PrefixedStorageKey;
 use sp_trie::Recorder;
-use crate::changes_trie::{AnchorBlockId, ConfigurationRange, RootsStorage, Storage, BlockNumber};
-use crate::changes_trie::input::{DigestIndex, ExtrinsicIndex, DigestIndexValue, ExtrinsicIndexValue};
+use crate::changes_trie::{
+	AnchorBlockId, ConfigurationRange, RootsStorage, Storage, BlockNumber, TrieBackendStorageAdapter,
+};
+use crate::changes_trie::input::{DigestIndex, ExtrinsicIndex, DigestIndexValue, ExtrinsicIndexValue, InputKey};
 use crate::changes_trie::storage::{TrieBackendAdapter, InMemoryStorage};
 use crate::changes_trie::input::ChildIndex;
 use crate::changes_trie::surface_iterator::{surface_iterator, SurfaceIterator};
@@ -72,6 +74,31 @@ pub fn key_changes<'a, H: Hasher, Number: BlockNumber>(
 	})
 }
 
+/// Return the keys of the top-level trie changed at the `anchor` block, as recorded in its
+/// changes trie. Only keys starting with `prefix` are returned.
+///
+/// Keys written with the value they already had are included.
+pub fn changed_keys<H: Hasher, Number: BlockNumber>(
+	storage: &dyn Storage<H, Number>,
+	anchor: &AnchorBlockId<H::Out, Number>,
+	prefix: &[u8],
+) -> Result<Vec<Vec<u8>>, String> {
+	// blocks that have not changed anything have no changes trie
+	let root = match storage.root(anchor, anchor.number.clone())? {
+		Some(root) => root,
+		None => return Ok(Vec::new()),
+	};
+
+	let mut keys = Vec::new();
+	let trie_storage = TrieBackendEssence::<_, H>::new(TrieBackendStorageAdapter(storage), root);
+	trie_storage.for_keys_with_prefix(&ExtrinsicIndex::key_neutral_prefix(anchor.number.clone()), |key|
+		if let Ok(InputKey::ExtrinsicIndex::<Number>(index)) = Decode::decode(&mut &key[..]) {
+			if index.key.starts_with(prefix) {
+				keys.push(index.key);
+			}
+		});
+	Ok(keys)
+}
 
 /// Returns proof of changes of given key at given blocks range.
 /// `max` is the number of best known block.
@@ -639,4 +666,22 @@ mod tests {
 		).and_then(Result::from_iter);
 		assert_eq!(drilldown_result, Ok(vec![(79, 1), (63, 0)]));
 	}
+
+	#[test]
+	fn changed_keys_works() {
+		let (_, storage) = prepare_for_drilldown();
+		let changed_keys_at = |number, prefix: &[u8]| changed_keys::<BlakeTwo256, u64>(
+			&storage,
+			&AnchorBlockId { hash: Default::default(), number },
+			prefix,
+		);
+
+		// digest entries and child changes are not reported
+		assert_eq!(changed_keys_at(8, &[]), Ok(vec![vec![42]]));
+		assert_eq!(changed_keys_at(8, &[42]), Ok(vec![vec![42]]));
+		assert_eq!(changed_keys_at(8, &[43]), Ok(vec![]));
+		assert_eq!(changed_keys_at(4, &[]), Ok(vec![]));
+		assert_eq!(changed_keys_at(1, &[]), Ok(vec![]));
+		assert_eq!(changed_keys_at(5, &[]), Ok(vec![]));
+	}
 }
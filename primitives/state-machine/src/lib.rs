@@ -156,7 +156,7 @@ mod std_reexport {
 		BuildCache as ChangesTrieBuildCache,
 		CacheAction as ChangesTrieCacheAction,
 		ConfigurationRange as ChangesTrieConfigurationRange,
-		key_changes, key_changes_proof,
+		changed_keys as changes_trie_changed_keys, key_changes, key_changes_proof,
 		key_changes_proof_check, key_changes_proof_check_with_db,
 		prune as prune_changes_tries,
 		disabled_state as disabled_changes_trie_state,
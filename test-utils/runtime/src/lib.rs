@@ -367,6 +367,8 @@ cfg_if! {
 				fn test_multiple_arguments(data: Vec<u8>, other: Vec<u8>, num: u32);
 				/// Traces log "Hey I'm runtime."
 				fn do_trace_log();
+				/// Hashes a value `rounds` times, to run for a long time on purpose.
+				fn hash_rounds(rounds: u32) -> [u8; 32];
 			}
 		}
 	} else {
@@ -420,6 +422,8 @@ cfg_if! {
 				fn test_multiple_arguments(data: Vec<u8>, other: Vec<u8>, num: u32);
 				/// Traces log "Hey I'm runtime."
 				fn do_trace_log();
+				/// Hashes a value `rounds` times, to run for a long time on purpose.
+				fn hash_rounds(rounds: u32) -> [u8; 32];
 			}
 		}
 	}
@@ -757,6 +761,10 @@ cfg_if! {
 				fn do_trace_log() {
 					log::trace!("Hey I'm runtime");
 				}
+
+				fn hash_rounds(rounds: u32) -> [u8; 32] {
+					(0..rounds).fold([0; 32], |hash, _| sp_io::hashing::blake2_256(&hash))
+				}
 			}
 
 			impl sp_consensus_aura::AuraApi<Block, AuraId> for Runtime {
@@ -1014,6 +1022,10 @@ cfg_if! {
 				fn do_trace_log() {
 					log::trace!("Hey I'm runtime: {}", log::STATIC_MAX_LEVEL);
 				}
+
+				fn hash_rounds(rounds: u32) -> [u8; 32] {
+					(0..rounds).fold([0; 32], |hash, _| sp_io::hashing::blake2_256(&hash))
+				}
 			}
 
 			impl sp_consensus_aura::AuraApi<Block, AuraId> for Runtime {
@@ -126,6 +126,11 @@ pub fn default_config(task_executor: TaskExecutor, mut chain_spec: Box<dyn Chain
 		rpc_ws_max_connections: None,
 		rpc_cors: None,
 		rpc_methods: Default::default(),
+		rpc_call_timeout: None,
+		rpc_max_heavy_requests: None,
+		rpc_forbidden_calls: Vec::new(),
+		rpc_disable_compression: false,
+		rpc_reject_while_syncing: false,
 		prometheus_config: None,
 		telemetry_endpoints: None,
 		telemetry_external_transport: None,
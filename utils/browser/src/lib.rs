@@ -102,6 +102,11 @@ where
 		rpc_ws: Default::default(),
 		rpc_ws_max_connections: Default::default(),
 		rpc_methods: Default::default(),
+		rpc_call_timeout: Default::default(),
+		rpc_max_heavy_requests: Default::default(),
+		rpc_forbidden_calls: Default::default(),
+		rpc_disable_compression: Default::default(),
+		rpc_reject_while_syncing: Default::default(),
 		state_cache_child_ratio: Default::default(),
 		state_cache_size: Default::default(),
 		tracing_receiver: Default::default(),